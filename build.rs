@@ -1 +1,268 @@
-fn main() {}
\ No newline at end of file
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// All the audio is stored as IMA-ADPCM rather than the raw 8-bit samples `include_wav!` would
+/// give, which halves the size of the biggest things in the ROM.
+const ADPCM_TRACKS: &[&str] = &[
+    "BGM_Title",
+    "BGM_Menu",
+    "BGM_Fight",
+    "SingleRoll_1",
+    "SingleRoll_2",
+    "SingleRoll_3",
+    "SingleRoll_4",
+    "SingleRoll_5",
+    "MultiRoll_1",
+    "MultiRoll_2",
+    "MultiRoll_3",
+    "MultiRoll_4",
+    "MultiRoll_5",
+    "shoot",
+    "shot_hit",
+    "ship_explode",
+    "move_cursor",
+    "select",
+    "back",
+    "accept",
+    "shield_down",
+    "shield_up",
+    "shield_defend",
+    "disrupt",
+    "heal",
+    "send_burst_shield",
+    "burst_shield_hit",
+    "warning_tick",
+];
+
+/// Sound effects which only have one recording. A few variations of each are made so that hearing
+/// the same sound over and over during a battle doesn't get tiring.
+const VARIED_EFFECTS: &[&str] = &["shoot", "shot_hit", "shield_defend"];
+
+/// How much faster each variation plays, and how strongly it gets low pass filtered between 0
+/// and 1. Must match the number of `variation!`s for each sound in `src/sfx/bank.rs`.
+const VARIATIONS: &[(f32, f32)] = &[(0.94, 0.3), (1.05, 0.0), (1.0, 0.55)];
+
+const SAMPLE_RATE: u32 = 32768;
+
+/// How long the wave the decoder's tests decode is. Odd, so that the padding gets tested too.
+const TEST_WAVE_SAMPLES: usize = 2501;
+
+/// Must match `BLOCK_SAMPLES` in `src/sfx/adpcm.rs`
+const BLOCK_SAMPLES: usize = 1024;
+
+const INDEX_TABLE: [i32; 16] = [-1, -1, -1, -1, 2, 4, 6, 8, -1, -1, -1, -1, 2, 4, 6, 8];
+
+const STEP_TABLE: [i32; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66,
+    73, 80, 88, 97, 107, 118, 130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408, 449,
+    494, 544, 598, 658, 724, 796, 876, 963, 1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272,
+    2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132, 7845, 8630, 9493,
+    10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794, 32767,
+];
+
+fn main() {
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR should be set by cargo");
+
+    for track in ADPCM_TRACKS {
+        let wav_path = format!("sfx/{}.wav", track);
+        println!("cargo:rerun-if-changed={}", wav_path);
+
+        let wav =
+            fs::read(&wav_path).unwrap_or_else(|e| panic!("failed to read {}: {}", wav_path, e));
+        let samples = read_wav_samples(&wav, &wav_path);
+
+        fs::write(
+            Path::new(&out_dir).join(format!("{}.adpcm", track)),
+            encode_adpcm(&samples),
+        )
+        .expect("failed to write encoded audio");
+    }
+
+    for effect in VARIED_EFFECTS {
+        let wav_path = format!("sfx/{}.wav", effect);
+        println!("cargo:rerun-if-changed={}", wav_path);
+
+        let wav =
+            fs::read(&wav_path).unwrap_or_else(|e| panic!("failed to read {}: {}", wav_path, e));
+        let samples = read_wav_samples(&wav, &wav_path);
+
+        for (i, &(speed, filter)) in VARIATIONS.iter().enumerate() {
+            fs::write(
+                Path::new(&out_dir).join(format!("{}_{}.adpcm", effect, i + 1)),
+                encode_adpcm(&make_variation(&samples, speed, filter)),
+            )
+            .expect("failed to write sound effect variation");
+        }
+    }
+
+    let test_wave: Vec<i16> = (0..TEST_WAVE_SAMPLES).map(test_wave).collect();
+    fs::write(
+        Path::new(&out_dir).join("test_wave.adpcm"),
+        encode_adpcm(&test_wave),
+    )
+    .expect("failed to write the test wave");
+}
+
+/// A triangle wave for the decoder's tests to check they get back. Must match `test_wave` in
+/// `src/sfx/adpcm.rs`.
+fn test_wave(i: usize) -> i16 {
+    let phase = (i % 256) as i16;
+    let rising = if phase < 128 { phase } else { 256 - phase };
+
+    rising * 128 - 8192
+}
+
+/// Resamples and filters a sound
+fn make_variation(samples: &[i16], speed: f32, filter: f32) -> Vec<i16> {
+    let length = (samples.len() as f32 / speed) as usize;
+    let mut filtered = 0.0;
+
+    (0..length)
+        .map(|i| {
+            let position = i as f32 * speed;
+            let index = position as usize;
+            let fraction = position - index as f32;
+
+            let current = samples[index.min(samples.len() - 1)] as f32;
+            let next = samples[(index + 1).min(samples.len() - 1)] as f32;
+            let sample = current + (next - current) * fraction;
+
+            filtered = filtered * filter + sample * (1.0 - filter);
+
+            filtered.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16
+        })
+        .collect()
+}
+
+/// Reads a mono wav file into 16-bit samples
+fn read_wav_samples(wav: &[u8], path: &str) -> Vec<i16> {
+    assert!(
+        wav.len() >= 12 && &wav[0..4] == b"RIFF" && &wav[8..12] == b"WAVE",
+        "{} is not a wav file",
+        path
+    );
+
+    let mut bits_per_sample = None;
+    let mut data = None;
+
+    let mut offset = 12;
+    while offset + 8 <= wav.len() {
+        let id = &wav[offset..offset + 4];
+        let size = u32::from_le_bytes([
+            wav[offset + 4],
+            wav[offset + 5],
+            wav[offset + 6],
+            wav[offset + 7],
+        ]) as usize;
+        let chunk = &wav[offset + 8..(offset + 8 + size).min(wav.len())];
+
+        if id == b"fmt " {
+            let channels = u16::from_le_bytes([chunk[2], chunk[3]]);
+            let sample_rate = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+
+            assert_eq!(channels, 1, "{} should be mono", path);
+            assert_eq!(
+                sample_rate, SAMPLE_RATE,
+                "{} has the wrong sample rate",
+                path
+            );
+
+            bits_per_sample = Some(u16::from_le_bytes([chunk[14], chunk[15]]));
+        } else if id == b"data" {
+            data = Some(chunk);
+        }
+
+        offset += 8 + size + (size & 1);
+    }
+
+    let data = data.unwrap_or_else(|| panic!("{} has no data", path));
+
+    match bits_per_sample {
+        Some(8) => data.iter().map(|&s| (s as i16 - 128) << 8).collect(),
+        Some(16) => data
+            .chunks_exact(2)
+            .map(|s| i16::from_le_bytes([s[0], s[1]]))
+            .collect(),
+        _ => panic!("{} should be 8 or 16 bit", path),
+    }
+}
+
+/// The format is the number of samples as a u32, followed by blocks of `BLOCK_SAMPLES` samples.
+/// Each block starts with the decoder state (predictor as an i16, step index as a u8 and a byte
+/// of padding) so playback can start at any block, then has 2 samples per byte, low nibble first.
+/// Odd length sounds get their last sample repeated so that every byte holds two samples.
+fn encode_adpcm(samples: &[i16]) -> Vec<u8> {
+    let mut samples = samples.to_vec();
+    if samples.len() % 2 == 1 {
+        samples.push(samples[samples.len() - 1]);
+    }
+
+    let mut output = Vec::with_capacity(4 + samples.len() / 2 + samples.len() / BLOCK_SAMPLES * 4);
+    output.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+
+    let mut predictor = 0i32;
+    let mut step_index = 0i32;
+
+    for block in samples.chunks(BLOCK_SAMPLES) {
+        output.extend_from_slice(&(predictor as i16).to_le_bytes());
+        output.push(step_index as u8);
+        output.push(0);
+
+        for pair in block.chunks(2) {
+            let mut byte = 0;
+
+            for (i, &sample) in pair.iter().enumerate() {
+                let nibble = encode_sample(sample as i32, &mut predictor, &mut step_index);
+                byte |= nibble << (i * 4);
+            }
+
+            output.push(byte);
+        }
+    }
+
+    output
+}
+
+fn encode_sample(sample: i32, predictor: &mut i32, step_index: &mut i32) -> u8 {
+    let step = STEP_TABLE[*step_index as usize];
+
+    let mut diff = sample - *predictor;
+    let mut nibble = 0;
+    if diff < 0 {
+        nibble = 8;
+        diff = -diff;
+    }
+
+    if diff >= step {
+        nibble |= 4;
+        diff -= step;
+    }
+    if diff >= step / 2 {
+        nibble |= 2;
+        diff -= step / 2;
+    }
+    if diff >= step / 4 {
+        nibble |= 1;
+    }
+
+    // track what the decoder will reconstruct so the error doesn't build up
+    let mut delta = step >> 3;
+    if nibble & 4 != 0 {
+        delta += step;
+    }
+    if nibble & 2 != 0 {
+        delta += step >> 1;
+    }
+    if nibble & 1 != 0 {
+        delta += step >> 2;
+    }
+    if nibble & 8 != 0 {
+        delta = -delta;
+    }
+
+    *predictor = (*predictor + delta).clamp(i16::MIN as i32, i16::MAX as i32);
+    *step_index = (*step_index + INDEX_TABLE[nibble as usize]).clamp(0, 88);
+
+    nibble
+}
@@ -1,5 +1,6 @@
 use agb::{
     display::tiled::{RegularMap, TileFormat, TileSet, TileSetting, VRamManager},
+    fixnum::Vector2D,
     include_gfx, rng,
 };
 
@@ -92,6 +93,8 @@ pub struct StarBackground<'a> {
 
     background1_timer: u32,
     background2_timer: u32,
+
+    shake: Vector2D<i32>,
 }
 
 impl<'a> StarBackground<'a> {
@@ -110,6 +113,8 @@ impl<'a> StarBackground<'a> {
 
             background1_timer: 0,
             background2_timer: 0,
+
+            shake: (0, 0).into(),
         }
     }
 
@@ -130,6 +135,24 @@ impl<'a> StarBackground<'a> {
         self.background2_timer -= 1;
     }
 
+    /// Moves the stars by `shake` from where they'd otherwise be, to shake the screen
+    pub fn set_shake(&mut self, shake: Vector2D<i32>) {
+        let change = shake - self.shake;
+        self.shake = shake;
+
+        // scrolling one way moves what's on screen the other way
+        for background in [&mut *self.background1, &mut *self.background2] {
+            let scroll = background.scroll_pos();
+            background.set_scroll_pos(
+                (
+                    (scroll.x as i32 - change.x) as u16,
+                    (scroll.y as i32 - change.y) as u16,
+                )
+                    .into(),
+            );
+        }
+    }
+
     pub fn commit(&mut self, vram: &mut VRamManager) {
         self.background1.commit(vram);
         self.background2.commit(vram);
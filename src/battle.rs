@@ -1,8 +1,10 @@
-use crate::sfx::Sfx;
-use crate::{
-    graphics::SELECT_BOX, level_generation::generate_attack, Agb, EnemyAttackType, Face, PlayerDice,
-};
+use crate::progress::achievements::AchievementToast;
+use crate::progress::GameEvent;
+use crate::sfx::{Side, SoundEvent};
+use crate::ui::{CursorStyle, Focus, FocusEvent, FocusSounds, Navigation};
+use crate::{level_generation::generate_attack, Agb, EnemyAttackType, Face, PlayerDice};
 use agb::display::tiled::RegularMap;
+use agb::fixnum::Vector2D;
 use agb::{hash_map::HashMap, input::Button};
 use alloc::vec;
 use alloc::vec::Vec;
@@ -14,6 +16,10 @@ mod display;
 pub(super) const MALFUNCTION_COOLDOWN_FRAMES: u32 = 3 * 60;
 const ROLL_TIME_FRAMES_ALL: u32 = 2 * 60;
 const ROLL_TIME_FRAMES_ONE: u32 = 60 / 8;
+/// How close an enemy attack needs to be to firing before the battle music gets more intense
+const DANGER_COOLDOWN_FRAMES: u32 = 2 * 60;
+/// How close an enemy attack needs to be to firing before the warning ticks start, if they're on
+const WARNING_COOLDOWN_FRAMES: u32 = 3 * 60;
 
 /// A face of the rolled die and it's cooldown (should it be a malfunction)
 #[derive(Debug)]
@@ -71,6 +77,8 @@ pub enum Action {
     EnemyShoot { damage: u32 },
     EnemyShield { amount: u32 },
     EnemyHeal { amount: u32 },
+    PlayerShieldBreak { amount: u32 },
+    EnemyShieldBreak { amount: u32 },
 }
 
 #[derive(Debug)]
@@ -115,6 +123,13 @@ impl RolledDice {
         })
     }
 
+    fn number_rolling(&self) -> u32 {
+        self.rolls
+            .iter()
+            .filter(|state| matches!(state, DieState::Rolling(..)))
+            .count() as u32
+    }
+
     fn faces_to_render(&self) -> impl Iterator<Item = (Face, Option<u32>)> + '_ {
         self.rolls.iter().map(|rolled_die| match rolled_die {
             DieState::Rolling(_, face, _previous_face) => (*face, None),
@@ -305,6 +320,8 @@ struct EnemyAttackState {
     attack: EnemyAttack,
     cooldown: u32,
     max_cooldown: u32,
+    /// Frames until the next warning tick
+    warning_timer: u32,
 }
 
 impl EnemyAttackState {
@@ -334,6 +351,44 @@ impl EnemyAttackState {
 
         None
     }
+
+    /// Whether a warning tick should play this frame. The ticks get closer together the closer
+    /// the attack is to firing.
+    fn should_warn(&mut self) -> bool {
+        if self.cooldown >= WARNING_COOLDOWN_FRAMES {
+            return false;
+        }
+
+        if self.warning_timer > 0 {
+            self.warning_timer -= 1;
+            return false;
+        }
+
+        self.warning_timer = (self.cooldown / 4).clamp(6, 30);
+        true
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum PopupKind {
+    Damage,
+    Heal,
+    Blocked,
+}
+
+/// A number to float up from one of the ships
+#[derive(Clone, Copy, Debug)]
+struct NumberPopup {
+    side: Side,
+    kind: PopupKind,
+    amount: u32,
+}
+
+/// A ship's hull taking damage, to shake the screen and flash the ship for
+#[derive(Clone, Copy, Debug)]
+struct Hit {
+    side: Side,
+    damage: u32,
 }
 
 #[derive(Debug)]
@@ -351,19 +406,51 @@ pub struct CurrentBattleState {
     player_dice: PlayerDice,
     attacks: [Option<EnemyAttackState>; 2],
     current_level: u32,
+    damage_taken: u32,
+    events: Vec<GameEvent>,
+    sounds: Vec<SoundEvent>,
+    popups: Vec<NumberPopup>,
+    hits: Vec<Hit>,
+    /// How many times the player has rerolled a die or accepted the dice in this battle
+    rerolls: u32,
+    accepts: u32,
 }
 
 impl CurrentBattleState {
     fn accept_rolls(&mut self) -> Vec<Action> {
-        self.rolled_dice.accept_rolls(&self.player_dice)
+        let faces = self.rolled_dice.faces_for_accepting().collect();
+        let rolling_before = self.rolled_dice.number_rolling();
+        let actions = self.rolled_dice.accept_rolls(&self.player_dice);
+
+        self.accepts += 1;
+        self.events.push(GameEvent::DiceAccepted {
+            faces,
+            actions: actions.clone(),
+        });
+        self.events.push(GameEvent::DiceRolled {
+            count: self.rolled_dice.number_rolling() - rolling_before,
+        });
+
+        actions
     }
 
     fn roll_die(&mut self, die_index: usize, time: u32, is_after_accept: bool) {
+        let rolling_before = self.rolled_dice.number_rolling();
+
         self.rolled_dice
             .roll_die(die_index, time, is_after_accept, &self.player_dice);
+
+        let count = self.rolled_dice.number_rolling() - rolling_before;
+        if count > 0 {
+            if !is_after_accept {
+                self.rerolls += 1;
+            }
+
+            self.events.push(GameEvent::DiceRolled { count });
+        }
     }
 
-    fn update(&mut self) -> Vec<Action> {
+    fn update(&mut self, attack_warnings: bool) -> Vec<Action> {
         let mut actions = vec![];
 
         for attack in self.attacks.iter_mut() {
@@ -371,12 +458,16 @@ impl CurrentBattleState {
                 if let Some(action) = attack_state.update() {
                     attack.take();
                     actions.push(action);
+                } else if attack_warnings && attack_state.should_warn() {
+                    self.sounds
+                        .push(SoundEvent::AttackWarning(attack_state.attack_type()));
                 }
             } else if let Some(generated_attack) = generate_attack(self.current_level) {
                 attack.replace(EnemyAttackState {
                     attack: generated_attack.attack,
                     cooldown: generated_attack.cooldown,
                     max_cooldown: generated_attack.cooldown,
+                    warning_timer: 0,
                 });
             }
         }
@@ -384,15 +475,48 @@ impl CurrentBattleState {
         actions
     }
 
+    /// Whether the player is low on health or about to get shot
+    fn is_in_danger(&self) -> bool {
+        self.player.health * 4 <= self.player.max_health
+            || self.attacks.iter().flatten().any(|attack| {
+                attack.attack_type() == EnemyAttackType::Attack
+                    && attack.cooldown < DANGER_COOLDOWN_FRAMES
+            })
+    }
+
     fn update_dice(&mut self) {
         self.rolled_dice.update(&self.player_dice);
     }
 
-    fn apply_action(&mut self, action: Action, sfx: &mut Sfx) -> Option<Action> {
+    fn popup(&mut self, side: Side, kind: PopupKind, amount: u32) {
+        if amount > 0 {
+            self.popups.push(NumberPopup { side, kind, amount });
+        }
+    }
+
+    fn hit(&mut self, side: Side, damage: u32) {
+        if damage > 0 {
+            self.hits.push(Hit { side, damage });
+        }
+    }
+
+    fn damage_enemy(&mut self, damage: u32) {
+        let health_before = self.enemy.health;
+        self.enemy.health = self.enemy.health.saturating_sub(damage);
+        let health_lost = health_before - self.enemy.health;
+
+        self.events.push(GameEvent::DamageDealt(health_lost));
+        self.popup(Side::Enemy, PopupKind::Damage, health_lost);
+        self.hit(Side::Enemy, damage);
+    }
+
+    fn apply_action(&mut self, action: Action) -> Option<Action> {
+        self.events.push(GameEvent::ActionApplied(action.clone()));
+
         match action {
             Action::PlayerActivateShield { amount } => {
                 if amount > self.player.shield_count {
-                    sfx.shield_up();
+                    self.sounds.push(SoundEvent::ShieldUp(Side::Player));
                 }
 
                 self.player.shield_count = self.player.shield_count.max(amount);
@@ -400,13 +524,17 @@ impl CurrentBattleState {
             }
             Action::PlayerShoot { damage, piercing } => {
                 if self.enemy.shield_count <= piercing {
-                    self.enemy.health = self.enemy.health.saturating_sub(damage);
-                    sfx.shot_hit();
+                    self.damage_enemy(damage);
+                    self.sounds.push(SoundEvent::ShotHit(Side::Enemy));
                 } else if self.enemy.shield_count <= damage {
-                    self.enemy.shield_count = 0; // TODO: Dispatch action of drop shield to animate that
-                    sfx.shield_down();
+                    let amount = self.enemy.shield_count;
+                    self.enemy.shield_count = 0;
+                    self.popup(Side::Enemy, PopupKind::Blocked, damage);
+
+                    return Some(Action::EnemyShieldBreak { amount });
                 } else {
-                    sfx.shield_defend();
+                    self.sounds.push(SoundEvent::ShieldDefend(Side::Enemy));
+                    self.popup(Side::Enemy, PopupKind::Blocked, damage);
                 }
 
                 None
@@ -417,58 +545,90 @@ impl CurrentBattleState {
                     attack.max_cooldown = attack.cooldown.max(attack.max_cooldown);
                 }
 
-                sfx.disrupt();
+                self.sounds.push(SoundEvent::Disrupt);
 
                 None
             }
             Action::PlayerHeal { amount } => {
+                let health_before = self.player.health;
                 self.player.health = self.player.max_health.min(self.player.health + amount);
-                sfx.heal();
+                self.sounds.push(SoundEvent::Heal(Side::Player));
+                self.popup(
+                    Side::Player,
+                    PopupKind::Heal,
+                    self.player.health - health_before,
+                );
                 None
             }
             Action::EnemyShoot { damage } => {
                 if self.player.shield_count == 0 {
+                    let health_before = self.player.health;
                     self.player.health = self.player.health.saturating_sub(damage);
-                    sfx.shot_hit();
+                    self.damage_taken += damage;
+                    self.sounds.push(SoundEvent::ShotHit(Side::Player));
+                    self.popup(
+                        Side::Player,
+                        PopupKind::Damage,
+                        health_before - self.player.health,
+                    );
+                    self.hit(Side::Player, damage);
                 } else if self.player.shield_count <= damage {
-                    self.player.shield_count = 0; // TODO: Dispatch action of drop shield to animate that
-                    sfx.shield_down();
+                    let amount = self.player.shield_count;
+                    self.player.shield_count = 0;
+                    self.events.push(GameEvent::DamageBlocked(damage));
+                    self.popup(Side::Player, PopupKind::Blocked, damage);
+
+                    return Some(Action::PlayerShieldBreak { amount });
                 } else {
-                    sfx.shield_defend();
+                    self.events.push(GameEvent::DamageBlocked(damage));
+                    self.sounds.push(SoundEvent::ShieldDefend(Side::Player));
+                    self.popup(Side::Player, PopupKind::Blocked, damage);
                 }
 
                 None
             }
             Action::EnemyShield { amount } => {
                 if amount > self.enemy.shield_count {
-                    sfx.shield_up();
+                    self.sounds.push(SoundEvent::ShieldUp(Side::Enemy));
                 }
 
                 self.enemy.shield_count = self.enemy.shield_count.max(amount);
                 None
             }
             Action::EnemyHeal { amount } => {
+                let health_before = self.enemy.health;
                 self.enemy.health = self.enemy.max_health.min(self.enemy.health + amount);
-                sfx.heal();
+                self.sounds.push(SoundEvent::Heal(Side::Enemy));
+                self.popup(
+                    Side::Enemy,
+                    PopupKind::Heal,
+                    self.enemy.health - health_before,
+                );
                 None
             }
             Action::PlayerBurstShield { multiplier } => {
                 let damage =
                     self.player.shield_count * (self.player.shield_count + 1) * multiplier / 2;
                 self.player.shield_count = 0;
-                sfx.send_burst_shield();
 
                 Some(Action::PlayerSendBurstShield { damage })
             }
             Action::PlayerSendBurstShield { damage } => {
+                let amount = self.enemy.shield_count;
                 self.enemy.shield_count = 0;
-                self.enemy.health = self.enemy.health.saturating_sub(damage);
+                self.damage_enemy(damage);
 
-                sfx.burst_shield_hit();
-                sfx.shield_down();
+                self.sounds.push(SoundEvent::BurstShieldHit);
 
-                None
+                if amount > 0 {
+                    Some(Action::EnemyShieldBreak { amount })
+                } else {
+                    None
+                }
             }
+            // The shields already went down when they got hit, these are only dispatched so the
+            // display can show them breaking
+            Action::PlayerShieldBreak { .. } | Action::EnemyShieldBreak { .. } => None,
         }
     }
 }
@@ -479,6 +639,11 @@ pub(crate) enum BattleResult {
     Loss,
 }
 
+/// Where the help text is scrolled to, moved over by `shake` along with everything else
+fn help_text_scroll(shake: Vector2D<i32>) -> Vector2D<u16> {
+    ((-17 - shake.x) as u16, (-98 - shake.y) as u16).into()
+}
+
 pub(crate) fn battle_screen(
     agb: &mut Agb,
     player_dice: PlayerDice,
@@ -488,17 +653,12 @@ pub(crate) fn battle_screen(
     agb.sfx.battle();
     agb.sfx.frame();
 
-    help_background.set_scroll_pos((u16::MAX - 16, u16::MAX - 97).into());
+    help_background.set_scroll_pos(help_text_scroll((0, 0).into()));
     crate::background::load_help_text(&mut agb.vram, help_background, 1, (0, 0));
     crate::background::load_help_text(&mut agb.vram, help_background, 2, (0, 1));
 
     let obj = &agb.obj;
 
-    let mut select_box_obj = agb.obj.object(agb.obj.sprite(SELECT_BOX.sprite(0)));
-    select_box_obj.show();
-
-    let num_dice = player_dice.dice.len();
-
     let enemy_health = 5 + current_level * agb::rng::gen().rem_euclid(4) as u32;
 
     let mut current_battle_state = CurrentBattleState {
@@ -522,91 +682,150 @@ pub(crate) fn battle_screen(
         player_dice: player_dice.clone(),
         attacks: [None, None],
         current_level,
+        damage_taken: 0,
+        events: vec![],
+        sounds: vec![],
+        popups: vec![],
+        hits: vec![],
+        rerolls: 0,
+        accepts: 0,
     };
 
-    let mut battle_screen_display = BattleScreenDisplay::new(obj, &current_battle_state);
+    let mut battle_screen_display = BattleScreenDisplay::new(
+        obj,
+        &current_battle_state,
+        agb.settings.battle_speed,
+        agb.settings.hit_effects,
+    );
+    let mut achievement_toast = AchievementToast::new((72, 4).into());
     agb.sfx.frame();
 
-    let mut selected_die = 0usize;
+    let mut dice_focus = Focus::new(
+        Navigation::Row,
+        (0..player_dice.dice.len())
+            .map(|i| (i as i32 * 40 + 28 - 4, 120 - 4).into())
+            .collect(),
+        CursorStyle::SelectBox,
+        FocusSounds {
+            moved: Some(SoundEvent::MoveCursor),
+            selected: Some(SoundEvent::Roll),
+            back: None,
+        },
+        obj,
+    );
     let mut input = agb::input::ButtonController::new();
-    let mut counter = 0usize;
+    let mut frame = 0usize;
 
     loop {
-        counter = counter.wrapping_add(1);
+        agb.run_frames = agb.run_frames.saturating_add(1);
 
-        for action_to_apply in battle_screen_display.update(obj, &current_battle_state) {
-            if let Some(action_to_return) =
-                current_battle_state.apply_action(action_to_apply, &mut agb.sfx)
-            {
+        for action_to_apply in
+            battle_screen_display.update(obj, &current_battle_state, &mut agb.sfx)
+        {
+            if let Some(action_to_return) = current_battle_state.apply_action(action_to_apply) {
                 battle_screen_display.add_action(action_to_return, obj, &mut agb.sfx);
             }
         }
 
-        for action in current_battle_state.update() {
-            battle_screen_display.add_action(action, obj, &mut agb.sfx);
-        }
+        frame = frame.wrapping_add(1);
+        for _ in 0..agb.settings.battle_speed.steps_for_frame(frame) {
+            for action in current_battle_state.update(agb.settings.attack_warnings) {
+                battle_screen_display.add_action(action, obj, &mut agb.sfx);
+            }
 
-        current_battle_state.update_dice();
+            current_battle_state.update_dice();
+        }
 
         input.update();
 
-        if input.is_just_pressed(Button::LEFT) {
-            if selected_die == 0 {
-                selected_die = num_dice - 1;
-            } else {
-                selected_die -= 1;
-            }
-
-            agb.sfx.move_cursor();
+        if let Some(FocusEvent::Selected(die)) = dice_focus.update(&input, &mut agb.sfx, obj) {
+            current_battle_state.roll_die(die, ROLL_TIME_FRAMES_ONE, false);
         }
 
-        if input.is_just_pressed(Button::RIGHT) {
-            if selected_die == num_dice - 1 {
-                selected_die = 0;
-            } else {
-                selected_die += 1;
+        if input.is_just_pressed(Button::START) {
+            for action in current_battle_state.accept_rolls() {
+                battle_screen_display.add_action(action, obj, &mut agb.sfx);
             }
+            agb.sfx.play(SoundEvent::RollMulti);
+        }
 
-            agb.sfx.move_cursor();
+        for event in current_battle_state.events.drain(..) {
+            agb.progress.report(event);
         }
 
-        if input.is_just_pressed(Button::A) {
-            current_battle_state.roll_die(selected_die, ROLL_TIME_FRAMES_ONE, false);
-            agb.sfx.roll();
+        for sound in current_battle_state.sounds.drain(..) {
+            agb.sfx.play(sound);
         }
 
-        if input.is_just_pressed(Button::START) {
-            for action in current_battle_state.accept_rolls() {
-                battle_screen_display.add_action(action, obj, &mut agb.sfx);
-            }
-            agb.sfx.roll_multi();
+        for popup in current_battle_state.popups.drain(..) {
+            battle_screen_display.add_popup(popup, obj);
+        }
+
+        for hit in current_battle_state.hits.drain(..) {
+            battle_screen_display.add_hit(hit);
         }
 
-        select_box_obj
-            .set_y(120 - 4)
-            .set_x(selected_die as u16 * 40 + 28 - 4)
-            .set_sprite(agb.obj.sprite(SELECT_BOX.animation_sprite(counter / 10)));
+        achievement_toast.update(&mut agb.progress.achievements, obj);
 
+        let shake = battle_screen_display.shake_offset();
+        dice_focus.set_offset(shake);
+        help_background.set_scroll_pos(help_text_scroll(shake));
+        agb.star_background.set_shake(shake);
         agb.star_background.update();
+        agb.sfx.set_danger(current_battle_state.is_in_danger());
         agb.sfx.frame();
         agb.vblank.wait_for_vblank();
         help_background.commit(&mut agb.vram);
         help_background.show();
 
-        if current_battle_state.enemy.health == 0 {
-            agb.sfx.ship_explode();
-            help_background.hide();
-            crate::background::load_help_text(&mut agb.vram, help_background, 3, (0, 0));
-            crate::background::load_help_text(&mut agb.vram, help_background, 3, (0, 1));
-            return BattleResult::Win;
-        }
+        let destroyed = if current_battle_state.enemy.health == 0 {
+            Some(Side::Enemy)
+        } else if current_battle_state.player.health == 0 {
+            Some(Side::Player)
+        } else {
+            None
+        };
 
-        if current_battle_state.player.health == 0 {
-            agb.sfx.ship_explode();
+        if let Some(destroyed) = destroyed {
+            agb.progress.report(match destroyed {
+                Side::Enemy => GameEvent::BattleWon {
+                    damage_taken: current_battle_state.damage_taken,
+                },
+                Side::Player => GameEvent::BattleLost,
+            });
+
+            agb.sfx.set_danger(false);
+            dice_focus.hide();
             help_background.hide();
             crate::background::load_help_text(&mut agb.vram, help_background, 3, (0, 0));
             crate::background::load_help_text(&mut agb.vram, help_background, 3, (0, 1));
-            return BattleResult::Loss;
+
+            // let the explosion play out without taking any input
+            battle_screen_display.start_ending(destroyed, obj);
+            loop {
+                let finished =
+                    battle_screen_display.update_ending(obj, &current_battle_state, &mut agb.sfx);
+                achievement_toast.update(&mut agb.progress.achievements, obj);
+
+                agb.star_background
+                    .set_shake(battle_screen_display.shake_offset());
+                agb.star_background.update();
+                agb.sfx.frame();
+                agb.vblank.wait_for_vblank();
+                agb.obj.commit();
+                agb.star_background.commit(&mut agb.vram);
+
+                if finished {
+                    break;
+                }
+            }
+
+            agb.star_background.set_shake((0, 0).into());
+
+            return match destroyed {
+                Side::Enemy => BattleResult::Win,
+                Side::Player => BattleResult::Loss,
+            };
         }
 
         agb.obj.commit();
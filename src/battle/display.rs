@@ -1,21 +1,39 @@
 use agb::display::object::{Object, ObjectController};
+use agb::fixnum::{Num, Vector2D};
 use agb::rng;
 use alloc::vec;
 use alloc::vec::Vec;
 
-use crate::graphics::{BURST_BULLET, DISRUPT_BULLET, SHIELD};
-use crate::sfx::Sfx;
+use crate::graphics::{BURST_BULLET, DISRUPT_BULLET, HEAL_SPARKLE, SHIELD, SHIELD_DEBRIS};
+use crate::settings::BattleSpeed;
+use crate::sfx::{panning_for_x, Sfx, Side, SoundEvent, VoiceId};
 use crate::{
     graphics::{
-        FractionDisplay, HealthBar, NumberDisplay, BULLET_SPRITE, ENEMY_ATTACK_SPRITES,
+        BarColour, FractionDisplay, HealthBar, NumberDisplay, BULLET_SPRITE, ENEMY_ATTACK_SPRITES,
         FACE_SPRITES, SHIP_SPRITES,
     },
     EnemyAttackType, Ship,
 };
 
-use super::{Action, CurrentBattleState, EnemyAttackState, MALFUNCTION_COOLDOWN_FRAMES};
+use super::{
+    Action, CurrentBattleState, EnemyAttackState, Hit, NumberPopup, MALFUNCTION_COOLDOWN_FRAMES,
+};
+use ending::BattleEnding;
+use flash::HitFlash;
+use hud::BattleHud;
+use popups::NumberPopups;
+use shake::ScreenShake;
+
+mod ending;
+mod flash;
+mod hud;
+mod popups;
+mod shake;
 
 struct BattleScreenDisplayObjects<'a> {
+    player_ship: Object<'a>,
+    enemy_ship: Object<'a>,
+    enemy_ship_kind: Ship,
     dice: Vec<Object<'a>>,
     dice_cooldowns: Vec<HealthBar<'a>>,
     player_shield: Vec<Object<'a>>,
@@ -32,34 +50,84 @@ struct BattleScreenDisplayObjects<'a> {
 pub struct BattleScreenDisplay<'a> {
     objs: BattleScreenDisplayObjects<'a>,
     animations: Vec<AnimationStateHolder<'a>>,
+    popups: NumberPopups<'a>,
+    hud: BattleHud<'a>,
+    battle_speed: BattleSpeed,
+    frame: usize,
+
+    player_health_shown: u32,
+    enemy_health_shown: u32,
 
-    _misc_sprites: Vec<Object<'a>>,
+    shake: ScreenShake,
+    flash: HitFlash,
+
+    ending: Option<BattleEnding<'a>>,
 }
 
 const HEALTH_BAR_WIDTH: usize = 48;
+/// How many frames it takes a health bar to fill up by a point when healed
+const HEALTH_FILL_FRAMES: usize = 4;
+
+const PLAYER_X: u16 = 12;
+const ENEMY_X: u16 = 167;
+const SHIP_Y: u16 = 8;
+const PLAYER_HEALTHBAR_X: u16 = 18;
+const ENEMY_HEALTHBAR_X: u16 = 180;
+const BULLET_Y: i32 = 36;
+
+fn ship_x(side: Side) -> u16 {
+    match side {
+        Side::Player => PLAYER_X,
+        Side::Enemy => ENEMY_X,
+    }
+}
 
-impl<'a> BattleScreenDisplay<'a> {
-    pub fn new(obj: &'a ObjectController, current_battle_state: &CurrentBattleState) -> Self {
-        let mut misc_sprites = vec![];
-        let player_x = 12;
-        let player_y = 8;
-        let enemy_x = 167;
+fn die_position(i: usize) -> Vector2D<i32> {
+    (i as i32 * 40 + 28, 120).into()
+}
 
-        let player_sprite = SHIP_SPRITES.sprite_for_ship(Ship::Player);
-        let enemy_sprite = SHIP_SPRITES.sprite_for_ship(if rng::gen() % 2 == 0 {
+fn enemy_attack_position(i: usize) -> Vector2D<i32> {
+    (120, 56 + 32 * i as i32).into()
+}
+
+/// Where the health numbers go under a health bar starting at `healthbar_x`
+fn health_display_position(healthbar_x: u16) -> Vector2D<i32> {
+    (
+        (healthbar_x + HEALTH_BAR_WIDTH as u16 / 2 - 16) as i32,
+        SHIP_Y as i32,
+    )
+        .into()
+}
+
+/// Where the `i`th shield in front of a ship goes
+fn shield_x(side: Side, i: u16) -> u16 {
+    match side {
+        Side::Player => PLAYER_X + 18 + 11 * i,
+        Side::Enemy => ENEMY_X - 16 - 11 * i,
+    }
+}
+
+impl<'a> BattleScreenDisplay<'a> {
+    pub fn new(
+        obj: &'a ObjectController,
+        current_battle_state: &CurrentBattleState,
+        battle_speed: BattleSpeed,
+        hit_effects: bool,
+    ) -> Self {
+        let enemy_ship_kind = if rng::gen() % 2 == 0 {
             Ship::Drone
         } else {
             Ship::PilotedShip
-        });
+        };
+
+        let player_sprite = SHIP_SPRITES.sprite_for_ship(Ship::Player);
+        let enemy_sprite = SHIP_SPRITES.sprite_for_ship(enemy_ship_kind);
 
         let mut player_obj = obj.object(obj.sprite(player_sprite));
         let mut enemy_obj = obj.object(obj.sprite(enemy_sprite));
 
-        player_obj.set_x(player_x).set_y(player_y).set_z(1).show();
-        enemy_obj.set_x(enemy_x).set_y(player_y).set_z(1).show();
-
-        misc_sprites.push(player_obj);
-        misc_sprites.push(enemy_obj);
+        player_obj.set_x(PLAYER_X).set_y(SHIP_Y).set_z(1).show();
+        enemy_obj.set_x(ENEMY_X).set_y(SHIP_Y).set_z(1).show();
 
         let dice: Vec<_> = current_battle_state
             .rolled_dice
@@ -68,7 +136,7 @@ impl<'a> BattleScreenDisplay<'a> {
             .map(|(i, (face, _))| {
                 let mut die_obj = obj.object(obj.sprite(FACE_SPRITES.sprite_for_face(face)));
 
-                die_obj.set_y(120).set_x(i as u16 * 40 + 28).show();
+                die_obj.set_position(die_position(i)).show();
 
                 die_obj
             })
@@ -78,8 +146,12 @@ impl<'a> BattleScreenDisplay<'a> {
             .iter()
             .enumerate()
             .map(|(i, _)| {
-                let mut cooldown_bar =
-                    HealthBar::new((i as i32 * 40 + 28, 120 - 8).into(), 24, obj);
+                let mut cooldown_bar = HealthBar::new_coloured(
+                    die_position(i) - (0, 8).into(),
+                    24,
+                    BarColour::Cooldown,
+                    obj,
+                );
                 cooldown_bar.hide();
                 cooldown_bar
             })
@@ -92,8 +164,8 @@ impl<'a> BattleScreenDisplay<'a> {
             .map(|i| {
                 let mut shield_obj = obj.object(obj.sprite(shield_sprite));
                 shield_obj
-                    .set_x(player_x + 18 + 11 * i)
-                    .set_y(player_y)
+                    .set_x(shield_x(Side::Player, i))
+                    .set_y(SHIP_Y)
                     .hide();
 
                 shield_obj
@@ -105,8 +177,8 @@ impl<'a> BattleScreenDisplay<'a> {
             .map(|i| {
                 let mut shield_obj = obj.object(obj.sprite(shield_sprite));
                 shield_obj
-                    .set_x(enemy_x - 16 - 11 * i)
-                    .set_y(player_y)
+                    .set_x(shield_x(Side::Enemy, i))
+                    .set_y(SHIP_Y)
                     .set_hflip(true)
                     .hide();
 
@@ -114,35 +186,33 @@ impl<'a> BattleScreenDisplay<'a> {
             })
             .collect();
 
-        let player_healthbar_x = 18;
-        let enemy_healthbar_x = 180;
-        let player_healthbar = HealthBar::new(
-            (player_healthbar_x, player_y - 8).into(),
+        let mut player_healthbar = HealthBar::new(
+            (PLAYER_HEALTHBAR_X, SHIP_Y - 8).into(),
             HEALTH_BAR_WIDTH,
             obj,
         );
-        let enemy_healthbar = HealthBar::new(
-            (enemy_healthbar_x, player_y - 8).into(),
+        player_healthbar.enable_damage_ghost();
+        let mut enemy_healthbar = HealthBar::new(
+            (ENEMY_HEALTHBAR_X, SHIP_Y - 8).into(),
             HEALTH_BAR_WIDTH,
             obj,
         );
+        enemy_healthbar.enable_damage_ghost();
 
-        let player_health_display = FractionDisplay::new(
-            (
-                player_healthbar_x + HEALTH_BAR_WIDTH as u16 / 2 - 16,
-                player_y,
-            )
-                .into(),
-            3,
+        let mut player_health_display =
+            FractionDisplay::new(health_display_position(PLAYER_HEALTHBAR_X), 3, obj);
+        let mut enemy_health_display =
+            FractionDisplay::new(health_display_position(ENEMY_HEALTHBAR_X), 3, obj);
+
+        // start at the right health rather than counting up to it
+        player_health_display.set_value(
+            current_battle_state.player.health as usize,
+            current_battle_state.player.max_health as usize,
             obj,
         );
-        let enemy_health_display = FractionDisplay::new(
-            (
-                enemy_healthbar_x + HEALTH_BAR_WIDTH as u16 / 2 - 16,
-                player_y,
-            )
-                .into(),
-            3,
+        enemy_health_display.set_value(
+            current_battle_state.enemy.health as usize,
+            current_battle_state.enemy.max_health as usize,
             obj,
         );
 
@@ -152,22 +222,27 @@ impl<'a> BattleScreenDisplay<'a> {
                 let mut attack_obj = obj.object(
                     obj.sprite(ENEMY_ATTACK_SPRITES.sprite_for_attack(EnemyAttackType::Attack)),
                 );
+                attack_obj.hide();
 
-                let attack_obj_position = (120, 56 + 32 * i).into();
-                attack_obj.set_position(attack_obj_position).hide();
+                let position = enemy_attack_position(i);
 
                 let mut attack_cooldown =
-                    HealthBar::new(attack_obj_position + (32, 8).into(), 48, obj);
+                    HealthBar::new_coloured(position, 48, BarColour::Cooldown, obj);
                 attack_cooldown.hide();
 
-                let attack_number_display =
-                    NumberDisplay::new(attack_obj_position - (8, -10).into());
+                let attack_number_display = NumberDisplay::new(position);
 
-                EnemyAttackDisplay::new(attack_obj, attack_cooldown, attack_number_display)
+                let mut attack_display =
+                    EnemyAttackDisplay::new(attack_obj, attack_cooldown, attack_number_display);
+                attack_display.set_position(position);
+                attack_display
             })
             .collect();
 
         let objs = BattleScreenDisplayObjects {
+            player_ship: player_obj,
+            enemy_ship: enemy_obj,
+            enemy_ship_kind,
             dice,
             dice_cooldowns,
             player_shield,
@@ -185,8 +260,18 @@ impl<'a> BattleScreenDisplay<'a> {
             objs,
 
             animations: vec![],
+            popups: NumberPopups::default(),
+            hud: BattleHud::new(current_battle_state.current_level, enemy_ship_kind, obj),
+            battle_speed,
+            frame: 0,
+
+            player_health_shown: current_battle_state.player.health,
+            enemy_health_shown: current_battle_state.enemy.health,
+
+            shake: ScreenShake::new(hit_effects),
+            flash: HitFlash::new(hit_effects),
 
-            _misc_sprites: misc_sprites,
+            ending: None,
         }
     }
 
@@ -194,106 +279,279 @@ impl<'a> BattleScreenDisplay<'a> {
         &mut self,
         obj: &'a ObjectController,
         current_battle_state: &CurrentBattleState,
+        sfx: &mut Sfx,
     ) -> Vec<Action> {
-        for (i, player_shield) in self.objs.player_shield.iter_mut().enumerate() {
-            if i < current_battle_state.player.shield_count as usize {
-                player_shield
-                    .show()
-                    .set_sprite(obj.sprite(SHIELD.sprite(0)));
+        self.update_health(current_battle_state, obj);
+        self.hud.update(
+            current_battle_state.rerolls,
+            current_battle_state.accepts,
+            obj,
+        );
+
+        for (i, attack) in current_battle_state.attacks.iter().enumerate() {
+            self.objs.enemy_attack_display[i].update(attack, obj);
+        }
+
+        let mut actions_to_apply = vec![];
+
+        // update the dice display to display the current values
+        for ((die_obj, (current_face, cooldown)), cooldown_healthbar) in self
+            .objs
+            .dice
+            .iter_mut()
+            .zip(current_battle_state.rolled_dice.faces_to_render())
+            .zip(self.objs.dice_cooldowns.iter_mut())
+        {
+            die_obj.set_sprite(obj.sprite(FACE_SPRITES.sprite_for_face(current_face)));
+
+            if let Some(cooldown) = cooldown {
+                cooldown_healthbar
+                    .set_value((cooldown * 24 / MALFUNCTION_COOLDOWN_FRAMES) as usize, obj);
+                cooldown_healthbar.show();
             } else {
-                player_shield.hide();
+                cooldown_healthbar.hide();
             }
         }
 
-        for (i, player_shield) in self.objs.enemy_shield.iter_mut().enumerate() {
-            if i < current_battle_state.enemy.shield_count as usize {
-                player_shield
-                    .show()
-                    .set_sprite(obj.sprite(SHIELD.sprite(0)));
-            } else {
-                player_shield.hide();
+        self.frame = self.frame.wrapping_add(1);
+        for _ in 0..self.battle_speed.steps_for_frame(self.frame) {
+            // the shield animations draw over this, so it only happens on frames where they move
+            // on too. Otherwise they'd flicker when the battle is slowed down.
+            self.show_shields(current_battle_state, obj);
+
+            let mut animations_to_remove = vec![];
+            for (i, animation) in self.animations.iter_mut().enumerate() {
+                match animation.update(&mut self.objs, obj, current_battle_state, sfx) {
+                    AnimationUpdateState::RemoveWithAction(a) => {
+                        actions_to_apply.push(a);
+                        animations_to_remove.push(i);
+                    }
+                    AnimationUpdateState::Continue => {}
+                }
+            }
+
+            for &animation_to_remove in animations_to_remove.iter().rev() {
+                self.animations.swap_remove(animation_to_remove);
+            }
+        }
+
+        self.popups.update();
+
+        self.shake.update();
+        self.flash.update(self.shake.offset());
+        self.place_objects(self.shake.offset());
+
+        actions_to_apply
+    }
+
+    /// Shows the shields each ship has up, and hides the rest
+    fn show_shields(
+        &mut self,
+        current_battle_state: &CurrentBattleState,
+        obj: &'a ObjectController,
+    ) {
+        for (shields, shield_count) in [
+            (
+                &mut self.objs.player_shield,
+                current_battle_state.player.shield_count,
+            ),
+            (
+                &mut self.objs.enemy_shield,
+                current_battle_state.enemy.shield_count,
+            ),
+        ] {
+            for (i, shield) in shields.iter_mut().enumerate() {
+                if i < shield_count as usize {
+                    shield.show().set_sprite(obj.sprite(SHIELD.sprite(0)));
+                } else {
+                    shield.hide();
+                }
             }
         }
+    }
+
+    fn update_health(
+        &mut self,
+        current_battle_state: &CurrentBattleState,
+        obj: &'a ObjectController,
+    ) {
+        approach_health(
+            &mut self.player_health_shown,
+            current_battle_state.player.health,
+            self.frame,
+        );
+        approach_health(
+            &mut self.enemy_health_shown,
+            current_battle_state.enemy.health,
+            self.frame,
+        );
 
         self.objs.player_healthbar.set_value(
-            ((current_battle_state.player.health * HEALTH_BAR_WIDTH as u32)
+            ((self.player_health_shown * HEALTH_BAR_WIDTH as u32)
                 / current_battle_state.player.max_health) as usize,
             obj,
         );
 
         self.objs.enemy_healthbar.set_value(
-            ((current_battle_state.enemy.health * HEALTH_BAR_WIDTH as u32)
+            ((self.enemy_health_shown * HEALTH_BAR_WIDTH as u32)
                 / current_battle_state.enemy.max_health) as usize,
             obj,
         );
 
-        self.objs.player_health.set_value(
-            current_battle_state.player.health as usize,
+        self.objs.player_healthbar.update(obj);
+        self.objs.enemy_healthbar.update(obj);
+
+        self.objs.player_health.count_to(
+            self.player_health_shown as usize,
             current_battle_state.player.max_health as usize,
             obj,
         );
 
-        self.objs.enemy_health.set_value(
-            current_battle_state.enemy.health as usize,
+        self.objs.enemy_health.count_to(
+            self.enemy_health_shown as usize,
             current_battle_state.enemy.max_health as usize,
             obj,
         );
 
-        for (i, attack) in current_battle_state.attacks.iter().enumerate() {
-            self.objs.enemy_attack_display[i].update(attack, obj);
-        }
+        self.objs.player_health.update(obj);
+        self.objs.enemy_health.update(obj);
+    }
 
-        let mut actions_to_apply = vec![];
+    /// Puts everything where it belongs, moved over by `offset` to shake the screen. During the
+    /// ending the ships are left alone, since it moves them itself.
+    fn place_objects(&mut self, offset: Vector2D<i32>) {
+        if self.ending.is_none() {
+            self.place_ships(offset);
+        }
 
-        // update the dice display to display the current values
-        for ((die_obj, (current_face, cooldown)), cooldown_healthbar) in self
+        for (i, (die, cooldown)) in self
             .objs
             .dice
             .iter_mut()
-            .zip(current_battle_state.rolled_dice.faces_to_render())
             .zip(self.objs.dice_cooldowns.iter_mut())
+            .enumerate()
         {
-            die_obj.set_sprite(obj.sprite(FACE_SPRITES.sprite_for_face(current_face)));
+            let position = die_position(i) + offset;
+            die.set_position(position);
+            cooldown.set_position(position - (0, 8).into());
+        }
 
-            if let Some(cooldown) = cooldown {
-                cooldown_healthbar
-                    .set_value((cooldown * 24 / MALFUNCTION_COOLDOWN_FRAMES) as usize, obj);
-                cooldown_healthbar.show();
-            } else {
-                cooldown_healthbar.hide();
-            }
+        let healthbar_y = SHIP_Y as i32 - 8;
+        self.objs
+            .player_healthbar
+            .set_position(offset + (PLAYER_HEALTHBAR_X as i32, healthbar_y).into());
+        self.objs
+            .enemy_healthbar
+            .set_position(offset + (ENEMY_HEALTHBAR_X as i32, healthbar_y).into());
+        self.objs
+            .player_health
+            .set_position(health_display_position(PLAYER_HEALTHBAR_X) + offset);
+        self.objs
+            .enemy_health
+            .set_position(health_display_position(ENEMY_HEALTHBAR_X) + offset);
+
+        for (i, attack) in self.objs.enemy_attack_display.iter_mut().enumerate() {
+            attack.set_position(enemy_attack_position(i) + offset);
         }
 
-        let mut animations_to_remove = vec![];
-        for (i, animation) in self.animations.iter_mut().enumerate() {
-            match animation.update(&mut self.objs, obj, current_battle_state) {
-                AnimationUpdateState::RemoveWithAction(a) => {
-                    actions_to_apply.push(a);
-                    animations_to_remove.push(i);
-                }
-                AnimationUpdateState::Continue => {}
-            }
+        self.hud.set_offset(offset);
+        self.popups.set_offset(offset);
+
+        for animation in self.animations.iter_mut() {
+            animation.place(offset);
         }
+    }
 
-        for &animation_to_remove in animations_to_remove.iter().rev() {
-            self.animations.swap_remove(animation_to_remove);
+    /// Puts the ships and their shields where they belong, moved over by `offset`
+    fn place_ships(&mut self, offset: Vector2D<i32>) {
+        let x = |x: u16| (x as i32 + offset.x) as u16;
+        let y = (SHIP_Y as i32 + offset.y) as u16;
+
+        self.objs.player_ship.set_x(x(PLAYER_X)).set_y(y);
+        self.objs.enemy_ship.set_x(x(ENEMY_X)).set_y(y);
+
+        for (i, shield) in self.objs.player_shield.iter_mut().enumerate() {
+            shield.set_x(x(shield_x(Side::Player, i as u16))).set_y(y);
         }
 
-        actions_to_apply
+        for (i, shield) in self.objs.enemy_shield.iter_mut().enumerate() {
+            shield.set_x(x(shield_x(Side::Enemy, i as u16))).set_y(y);
+        }
+    }
+
+    /// Shakes the screen and flashes the ship which got hit
+    pub fn add_hit(&mut self, hit: Hit) {
+        self.shake.add(hit.damage);
+        self.flash.add(hit.side);
+    }
+
+    /// How far the backgrounds should move to go along with everything else shaking
+    pub fn shake_offset(&self) -> Vector2D<i32> {
+        self.shake.offset()
+    }
+
+    /// Blows up the ship on the losing side. Call `update_ending` every frame after this instead
+    /// of `update`, until it's done.
+    pub fn start_ending(&mut self, destroyed: Side, obj: &'a ObjectController) {
+        // anything still in flight would otherwise freeze in place
+        self.animations.clear();
+        // the ending moves the ships around itself, so they stop shaking
+        self.place_ships((0, 0).into());
+
+        self.ending = Some(BattleEnding::new(destroyed, &mut self.objs, obj));
+    }
+
+    /// Returns true once the ending has finished playing
+    pub fn update_ending(
+        &mut self,
+        obj: &'a ObjectController,
+        current_battle_state: &CurrentBattleState,
+        sfx: &mut Sfx,
+    ) -> bool {
+        self.frame = self.frame.wrapping_add(1);
+        self.update_health(current_battle_state, obj);
+        self.shake.update();
+        self.flash.update(self.shake.offset());
+        self.popups.update();
+
+        let finished = match &mut self.ending {
+            Some(ending) => ending.update(&mut self.objs, obj, sfx, &mut self.shake),
+            None => true,
+        };
+
+        self.place_objects(self.shake.offset());
+        finished
+    }
+
+    pub fn add_popup(&mut self, popup: NumberPopup, obj: &'a ObjectController) {
+        self.popups.add(popup, obj);
     }
 
     pub fn add_action(&mut self, action: Action, obj: &'a ObjectController, sfx: &mut Sfx) {
-        play_sound_for_action_start(&action, sfx);
+        let sound = play_sound_for_action_start(&action, sfx);
 
         self.animations
-            .push(AnimationStateHolder::for_action(action, obj));
+            .push(AnimationStateHolder::for_action(action, sound, obj));
+    }
+}
+
+/// Health bars fill back up a point at a time after a heal, but drop straight away when hit
+fn approach_health(shown: &mut u32, health: u32, frame: usize) {
+    if *shown > health {
+        *shown = health;
+    } else if *shown < health && frame % HEALTH_FILL_FRAMES == 0 {
+        *shown += 1;
     }
 }
 
-fn play_sound_for_action_start(action: &Action, sfx: &mut Sfx) {
+fn play_sound_for_action_start(action: &Action, sfx: &mut Sfx) -> Option<VoiceId> {
     match action {
-        Action::PlayerShoot { .. } | Action::EnemyShoot { .. } => sfx.shoot(),
-        _ => {}
+        Action::PlayerShoot { .. } => sfx.play(SoundEvent::Shoot(Side::Player)),
+        Action::EnemyShoot { .. } => sfx.play(SoundEvent::Shoot(Side::Enemy)),
+        Action::PlayerSendBurstShield { .. } => sfx.play(SoundEvent::SendBurstShield),
+        Action::PlayerShieldBreak { .. } => sfx.play(SoundEvent::ShieldDown(Side::Player)),
+        Action::EnemyShieldBreak { .. } => sfx.play(SoundEvent::ShieldDown(Side::Enemy)),
+        _ => None,
     }
 }
 
@@ -312,11 +570,24 @@ impl<'a> EnemyAttackDisplay<'a> {
         }
     }
 
+    pub fn set_position(&mut self, position: Vector2D<i32>) {
+        self.face.set_position(position);
+        self.cooldown.set_position(position + (32, 8).into());
+        self.number.set_position(position - (8, -10).into());
+    }
+
     pub fn update(&mut self, attack: &Option<EnemyAttackState>, obj: &'a ObjectController) {
         if let Some(attack) = attack {
             self.face.show().set_sprite(
                 obj.sprite(ENEMY_ATTACK_SPRITES.sprite_for_attack(attack.attack_type())),
             );
+            self.cooldown.set_colour(
+                match attack.attack_type() {
+                    EnemyAttackType::Shield => BarColour::Shield,
+                    EnemyAttackType::Attack | EnemyAttackType::Heal => BarColour::Cooldown,
+                },
+                obj,
+            );
             self.cooldown
                 .set_value((attack.cooldown * 48 / attack.max_cooldown) as usize, obj);
             self.cooldown.show();
@@ -336,15 +607,201 @@ enum AnimationState<'a> {
     PlayerDisrupt { bullet: Object<'a>, x: i32 },
     PlayerBurstShield { frame: usize },
     PlayerSendBurstShield { bullet: Object<'a>, x: i32 },
-    PlayerHeal {},
     EnemyShoot { bullet: Object<'a>, x: i32 },
     EnemyShield { amount: u32, frame: usize },
-    EnemyHeal {},
+    Heal(HealSparkles<'a>),
+    ShieldBreak(ShieldBreak<'a>),
+}
+
+const SPARKLES_PER_HEAL: usize = 6;
+/// Sparkles start one after the other over this many frames
+const SPARKLE_STAGGER_FRAMES: usize = 18;
+const SPARKLE_FRAMES: usize = 24;
+/// Which frames of the sparkle to go through as it rises, so it twinkles in and back out
+const SPARKLE_TWINKLE: [usize; 6] = [0, 1, 2, 1, 3, 0];
+
+struct Sparkle<'a> {
+    object: Object<'a>,
+    position: Vector2D<i32>,
+    /// How far it has floated up from `position`
+    rise: i32,
+    start: usize,
+}
+
+/// Twinkles rising up over a ship which is being healed. Like a bullet reaching its target, the
+/// heal lands once the last of them has finished.
+struct HealSparkles<'a> {
+    frame: usize,
+    sparkles: Vec<Sparkle<'a>>,
+}
+
+impl<'a> HealSparkles<'a> {
+    fn new(side: Side, obj: &'a ObjectController) -> Self {
+        let ship_x = ship_x(side) as i32;
+
+        let sparkles = (0..SPARKLES_PER_HEAL)
+            .map(|i| {
+                // somewhere along the bottom of the ship, which sits in the middle of its sprite
+                let x = ship_x + 14 + rng::gen().rem_euclid(36) - 4;
+                let y = SHIP_Y as i32 + 34 + rng::gen().rem_euclid(8) - 4;
+
+                Sparkle {
+                    object: obj.object(obj.sprite(HEAL_SPARKLE.sprite(0))),
+                    position: (x, y).into(),
+                    rise: 0,
+                    start: i * SPARKLE_STAGGER_FRAMES / SPARKLES_PER_HEAL,
+                }
+            })
+            .collect();
+
+        Self { frame: 0, sparkles }
+    }
+
+    /// Returns true once the heal should be applied
+    fn update(&mut self, obj: &'a ObjectController) -> bool {
+        for sparkle in self.sparkles.iter_mut() {
+            if self.frame < sparkle.start || self.frame >= sparkle.start + SPARKLE_FRAMES {
+                sparkle.object.hide();
+                continue;
+            }
+
+            let age = self.frame - sparkle.start;
+            let twinkle = SPARKLE_TWINKLE[age * SPARKLE_TWINKLE.len() / SPARKLE_FRAMES];
+
+            sparkle.rise = (age / 2) as i32;
+            sparkle
+                .object
+                .set_sprite(obj.sprite(HEAL_SPARKLE.sprite(twinkle)))
+                .show();
+        }
+
+        self.frame += 1;
+
+        self.frame >= SPARKLE_STAGGER_FRAMES + SPARKLE_FRAMES
+    }
+
+    fn place(&mut self, offset: Vector2D<i32>) {
+        for sparkle in self.sparkles.iter_mut() {
+            sparkle
+                .object
+                .set_position(sparkle.position - (0, sparkle.rise).into() + offset);
+        }
+    }
+}
+
+/// How many frames of the shield tag play when it breaks. These are the frames a shield goes up
+/// with, played from whole to nearly gone. The rest of the tag is for burst shields.
+const SHIELD_BREAK_FRAMES: usize = 4;
+const DEBRIS_PER_SHIELD: u32 = 2;
+const DEBRIS_FRAMES: usize = 28;
+/// Debris flickers for this many frames before it disappears
+const DEBRIS_FADE_FRAMES: usize = 10;
+
+/// A piece of a broken shield flying off
+struct Debris<'a> {
+    object: Object<'a>,
+    position: Vector2D<Num<i32, 8>>,
+    velocity: Vector2D<Num<i32, 8>>,
+    spin: usize,
+}
+
+/// The shields in front of a ship shattering after taking a hit
+struct ShieldBreak<'a> {
+    side: Side,
+    amount: u32,
+    frame: usize,
+    debris: Vec<Debris<'a>>,
+}
+
+impl<'a> ShieldBreak<'a> {
+    fn new(side: Side, amount: u32, obj: &'a ObjectController) -> Self {
+        // pieces get knocked back the way the shot was going, from the edge of the shield facing it
+        let (direction, edge) = match side {
+            Side::Player => (-1, 36),
+            Side::Enemy => (1, 26),
+        };
+
+        let debris = (0..amount * DEBRIS_PER_SHIELD)
+            .map(|i| {
+                let x = shield_x(side, (i / DEBRIS_PER_SHIELD) as u16) as i32 + edge - 4;
+                let y = SHIP_Y as i32 + 21 + rng::gen().rem_euclid(22) - 4;
+
+                let mut object = obj.object(obj.sprite(SHIELD_DEBRIS.sprite(0)));
+                object.set_hflip(side == Side::Enemy);
+
+                Debris {
+                    object,
+                    position: (Num::new(x), Num::new(y)).into(),
+                    velocity: (
+                        Num::from_raw(direction * (96 + rng::gen().rem_euclid(224))),
+                        Num::from_raw(rng::gen().rem_euclid(384) - 192),
+                    )
+                        .into(),
+                    spin: rng::gen().rem_euclid(4) as usize,
+                }
+            })
+            .collect();
+
+        Self {
+            side,
+            amount,
+            frame: 0,
+            debris,
+        }
+    }
+
+    /// Returns true once the last of the debris is gone
+    fn update(
+        &mut self,
+        shields: &mut [Object<'a>],
+        current_shields: u32,
+        obj: &'a ObjectController,
+    ) -> bool {
+        // the shield going up in reverse, leaving alone any which have been put back up since
+        if self.frame / 2 < SHIELD_BREAK_FRAMES {
+            for shield in shields
+                .iter_mut()
+                .take(self.amount as usize)
+                .skip(current_shields as usize)
+            {
+                shield
+                    .show()
+                    .set_sprite(obj.sprite(SHIELD.sprite(self.frame / 2)));
+            }
+        }
+
+        for piece in self.debris.iter_mut() {
+            piece.position += piece.velocity;
+            piece.object.set_sprite(
+                obj.sprite(SHIELD_DEBRIS.animation_sprite(piece.spin + self.frame / 3)),
+            );
+
+            if self.frame + DEBRIS_FADE_FRAMES >= DEBRIS_FRAMES && self.frame % 2 == 0 {
+                piece.object.hide();
+            } else {
+                piece.object.show();
+            }
+        }
+
+        self.frame += 1;
+
+        self.frame >= DEBRIS_FRAMES
+    }
+
+    fn place(&mut self, offset: Vector2D<i32>) {
+        for piece in self.debris.iter_mut() {
+            piece
+                .object
+                .set_position(offset + (piece.position.x.floor(), piece.position.y.floor()).into());
+        }
+    }
 }
 
 struct AnimationStateHolder<'a> {
     action: Action,
     state: AnimationState<'a>,
+    /// The sound started along with this animation, which follows anything that moves
+    sound: Option<VoiceId>,
 }
 
 enum AnimationUpdateState {
@@ -353,7 +810,7 @@ enum AnimationUpdateState {
 }
 
 impl<'a> AnimationStateHolder<'a> {
-    fn for_action(a: Action, obj: &'a ObjectController) -> Self {
+    fn for_action(a: Action, sound: Option<VoiceId>, obj: &'a ObjectController) -> Self {
         let state = match a {
             Action::PlayerActivateShield { amount, .. } => {
                 AnimationState::PlayerActivateShield { amount, frame: 0 }
@@ -366,7 +823,7 @@ impl<'a> AnimationStateHolder<'a> {
                 bullet: obj.object(obj.sprite(DISRUPT_BULLET)),
                 x: 64,
             },
-            Action::PlayerHeal { .. } => AnimationState::PlayerHeal {},
+            Action::PlayerHeal { .. } => AnimationState::Heal(HealSparkles::new(Side::Player, obj)),
             Action::PlayerBurstShield { .. } => AnimationState::PlayerBurstShield { frame: 0 },
             Action::PlayerSendBurstShield { .. } => AnimationState::PlayerSendBurstShield {
                 bullet: obj.object(obj.sprite(BURST_BULLET)),
@@ -377,10 +834,43 @@ impl<'a> AnimationStateHolder<'a> {
                 x: 175,
             },
             Action::EnemyShield { amount, .. } => AnimationState::EnemyShield { amount, frame: 0 },
-            Action::EnemyHeal { .. } => AnimationState::EnemyHeal {},
+            Action::EnemyHeal { .. } => AnimationState::Heal(HealSparkles::new(Side::Enemy, obj)),
+            Action::PlayerShieldBreak { amount } => {
+                AnimationState::ShieldBreak(ShieldBreak::new(Side::Player, amount, obj))
+            }
+            Action::EnemyShieldBreak { amount } => {
+                AnimationState::ShieldBreak(ShieldBreak::new(Side::Enemy, amount, obj))
+            }
         };
 
-        Self { action: a, state }
+        Self {
+            action: a,
+            state,
+            sound,
+        }
+    }
+
+    fn pan_sound_to(&self, x: i32, sfx: &mut Sfx) {
+        if let Some(sound) = self.sound {
+            sfx.set_panning(sound, panning_for_x(x));
+        }
+    }
+
+    /// Moves whatever the animation has on screen to where it should be, plus `offset`
+    fn place(&mut self, offset: Vector2D<i32>) {
+        match &mut self.state {
+            AnimationState::PlayerShoot { bullet, x }
+            | AnimationState::PlayerDisrupt { bullet, x }
+            | AnimationState::PlayerSendBurstShield { bullet, x }
+            | AnimationState::EnemyShoot { bullet, x } => {
+                bullet.set_position(offset + (*x, BULLET_Y).into());
+            }
+            AnimationState::Heal(sparkles) => sparkles.place(offset),
+            AnimationState::ShieldBreak(shield_break) => shield_break.place(offset),
+            AnimationState::PlayerActivateShield { .. }
+            | AnimationState::PlayerBurstShield { .. }
+            | AnimationState::EnemyShield { .. } => {}
+        }
     }
 
     fn update(
@@ -388,10 +878,18 @@ impl<'a> AnimationStateHolder<'a> {
         objs: &mut BattleScreenDisplayObjects<'a>,
         obj: &'a ObjectController,
         current_battle_state: &CurrentBattleState,
+        sfx: &mut Sfx,
     ) -> AnimationUpdateState {
+        if let AnimationState::PlayerShoot { x, .. }
+        | AnimationState::EnemyShoot { x, .. }
+        | AnimationState::PlayerSendBurstShield { x, .. } = &self.state
+        {
+            self.pan_sound_to(*x + 8, sfx);
+        }
+
         match &mut self.state {
             AnimationState::PlayerShoot { bullet, x } => {
-                bullet.show().set_x(*x as u16).set_y(36);
+                bullet.show();
                 *x += 4;
 
                 if *x > 180 {
@@ -401,7 +899,7 @@ impl<'a> AnimationStateHolder<'a> {
                 }
             }
             AnimationState::PlayerDisrupt { bullet, x } => {
-                bullet.show().set_x(*x as u16).set_y(36);
+                bullet.show();
                 *x += 2;
 
                 if *x > 180 {
@@ -432,7 +930,7 @@ impl<'a> AnimationStateHolder<'a> {
                 }
             }
             AnimationState::EnemyShoot { bullet, x } => {
-                bullet.show().set_hflip(true).set_x(*x as u16).set_y(36);
+                bullet.show().set_hflip(true);
                 *x -= 4;
 
                 if *x < 50 {
@@ -462,11 +960,12 @@ impl<'a> AnimationStateHolder<'a> {
                     AnimationUpdateState::Continue
                 }
             }
-            AnimationState::EnemyHeal {} => {
-                AnimationUpdateState::RemoveWithAction(self.action.clone()) // TODO: Animation for healing
-            }
-            AnimationState::PlayerHeal {} => {
-                AnimationUpdateState::RemoveWithAction(self.action.clone()) // TODO: Animation for healing
+            AnimationState::Heal(sparkles) => {
+                if sparkles.update(obj) {
+                    AnimationUpdateState::RemoveWithAction(self.action.clone())
+                } else {
+                    AnimationUpdateState::Continue
+                }
             }
             AnimationState::PlayerBurstShield { frame } => {
                 if *frame < 10 {
@@ -486,7 +985,7 @@ impl<'a> AnimationStateHolder<'a> {
                 }
             }
             AnimationState::PlayerSendBurstShield { bullet, x } => {
-                bullet.show().set_x(*x as u16).set_y(36);
+                bullet.show();
                 *x += 1;
 
                 if *x > 180 {
@@ -495,6 +994,24 @@ impl<'a> AnimationStateHolder<'a> {
                     AnimationUpdateState::Continue
                 }
             }
+            AnimationState::ShieldBreak(shield_break) => {
+                let (shields, current_shields) = match shield_break.side {
+                    Side::Player => (
+                        &mut objs.player_shield,
+                        current_battle_state.player.shield_count,
+                    ),
+                    Side::Enemy => (
+                        &mut objs.enemy_shield,
+                        current_battle_state.enemy.shield_count,
+                    ),
+                };
+
+                if shield_break.update(shields, current_shields, obj) {
+                    AnimationUpdateState::RemoveWithAction(self.action.clone())
+                } else {
+                    AnimationUpdateState::Continue
+                }
+            }
         }
     }
 }
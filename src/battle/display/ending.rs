@@ -0,0 +1,195 @@
+use agb::display::object::{Object, ObjectController};
+use agb::rng;
+use alloc::vec::Vec;
+
+use crate::graphics::{DESTROYED_BANNER, EXPLOSION, VICTORY_BANNER};
+use crate::sfx::{Sfx, Side, SoundEvent};
+
+use super::shake::ScreenShake;
+use super::{ship_x, BattleScreenDisplayObjects, PLAYER_X, SHIP_Y};
+
+/// How many frames each frame of the explosion sprite stays up for
+const EXPLOSION_FRAME_LENGTH: usize = 3;
+const SMALL_EXPLOSIONS: usize = 5;
+const SMALL_EXPLOSION_GAP_FRAMES: usize = 7;
+const FINAL_EXPLOSION_FRAME: usize = SMALL_EXPLOSIONS * SMALL_EXPLOSION_GAP_FRAMES;
+/// The ship disappears behind the last explosion once that is at its biggest
+const SHIP_GONE_FRAME: usize = FINAL_EXPLOSION_FRAME + 2 * EXPLOSION_FRAME_LENGTH;
+const BANNER_FRAME: usize = FINAL_EXPLOSION_FRAME + 12;
+/// The banner flickers for this many frames as it comes in
+const BANNER_FLICKER_FRAMES: usize = 12;
+const BANNER_Y: i32 = 60;
+const FLY_OFF_FRAME: usize = 60;
+/// How far the losing side's ship bobs up and down while it idles
+const BOB_OFFSETS: [i32; 4] = [0, 1, 0, -1];
+const ENDING_FRAMES: usize = 110;
+/// The last explosion shakes the screen as much as a hit this big
+const FINAL_EXPLOSION_SHAKE: u32 = 15;
+
+struct Explosion<'a> {
+    object: Object<'a>,
+    start: usize,
+    last: bool,
+}
+
+/// The losing ship blowing up, the winner flying off or hanging around, and a banner saying
+/// how it went
+pub(super) struct BattleEnding<'a> {
+    destroyed: Side,
+    frame: usize,
+    explosions: Vec<Explosion<'a>>,
+    banner: Vec<Object<'a>>,
+}
+
+impl<'a> BattleEnding<'a> {
+    pub fn new(
+        destroyed: Side,
+        objs: &mut BattleScreenDisplayObjects<'a>,
+        obj: &'a ObjectController,
+    ) -> Self {
+        for attack in objs.enemy_attack_display.iter_mut() {
+            attack.update(&None, obj);
+        }
+
+        let shields = match destroyed {
+            Side::Player => &mut objs.player_shield,
+            Side::Enemy => &mut objs.enemy_shield,
+        };
+        for shield in shields.iter_mut() {
+            shield.hide();
+        }
+
+        let ship_x = ship_x(destroyed) as i32;
+        let explosion_at = |x: i32, y: i32, start: usize, last: bool| {
+            let mut object = obj.object(obj.sprite(EXPLOSION.sprite(0)));
+            object.set_position((x - 16, y - 16).into()).hide();
+
+            Explosion {
+                object,
+                start,
+                last,
+            }
+        };
+
+        // little ones all over the ship, and then a big one in the middle of it. The ship sits in
+        // the middle of its sprite.
+        let mut explosions: Vec<_> = (0..SMALL_EXPLOSIONS)
+            .map(|i| {
+                explosion_at(
+                    ship_x + 14 + rng::gen().rem_euclid(36),
+                    SHIP_Y as i32 + 21 + rng::gen().rem_euclid(22),
+                    i * SMALL_EXPLOSION_GAP_FRAMES,
+                    false,
+                )
+            })
+            .collect();
+        explosions.push(explosion_at(
+            ship_x + 32,
+            SHIP_Y as i32 + 32,
+            FINAL_EXPLOSION_FRAME,
+            true,
+        ));
+
+        let banner_tag = match destroyed {
+            Side::Player => DESTROYED_BANNER,
+            Side::Enemy => VICTORY_BANNER,
+        };
+        let slices = banner_tag.sprites();
+        let banner_x = 120 - slices.len() as i32 * 16;
+        let banner = slices
+            .iter()
+            .enumerate()
+            .map(|(i, slice)| {
+                let mut object = obj.object(obj.sprite(slice));
+                object
+                    .set_position((banner_x + i as i32 * 32, BANNER_Y).into())
+                    .hide();
+                object
+            })
+            .collect();
+
+        Self {
+            destroyed,
+            frame: 0,
+            explosions,
+            banner,
+        }
+    }
+
+    /// Returns true once it's all over
+    pub fn update(
+        &mut self,
+        objs: &mut BattleScreenDisplayObjects<'a>,
+        obj: &'a ObjectController,
+        sfx: &mut Sfx,
+        shake: &mut ScreenShake,
+    ) -> bool {
+        for explosion in self.explosions.iter_mut() {
+            if self.frame == explosion.start {
+                if explosion.last {
+                    sfx.play(SoundEvent::ShipExplode(self.destroyed));
+                    shake.add(FINAL_EXPLOSION_SHAKE);
+                } else {
+                    sfx.play(SoundEvent::ShotHit(self.destroyed));
+                }
+            }
+
+            let sprite = self
+                .frame
+                .checked_sub(explosion.start)
+                .map(|age| age / EXPLOSION_FRAME_LENGTH)
+                .and_then(|i| EXPLOSION.sprites().get(i));
+
+            if let Some(sprite) = sprite {
+                explosion.object.set_sprite(obj.sprite(sprite)).show();
+            } else {
+                explosion.object.hide();
+            }
+        }
+
+        if self.frame == SHIP_GONE_FRAME {
+            match self.destroyed {
+                Side::Player => objs.player_ship.hide(),
+                Side::Enemy => objs.enemy_ship.hide(),
+            };
+        }
+
+        match self.destroyed {
+            // the player gets to fly off to the next battle
+            Side::Enemy => {
+                if self.frame == FLY_OFF_FRAME {
+                    for shield in objs.player_shield.iter_mut() {
+                        shield.hide();
+                    }
+                }
+
+                if let Some(t) = self.frame.checked_sub(FLY_OFF_FRAME) {
+                    let x = PLAYER_X as usize + t * t / 8;
+                    if x > 240 {
+                        objs.player_ship.hide();
+                    } else {
+                        objs.player_ship.set_x(x as u16);
+                    }
+                }
+            }
+            Side::Player => {
+                let bob = BOB_OFFSETS[(self.frame / 12) % BOB_OFFSETS.len()];
+                objs.enemy_ship.set_y((SHIP_Y as i32 + bob) as u16);
+            }
+        }
+
+        if let Some(age) = self.frame.checked_sub(BANNER_FRAME) {
+            for slice in self.banner.iter_mut() {
+                if age >= BANNER_FLICKER_FRAMES || age % 4 < 2 {
+                    slice.show();
+                } else {
+                    slice.hide();
+                }
+            }
+        }
+
+        self.frame += 1;
+
+        self.frame >= ENDING_FRAMES
+    }
+}
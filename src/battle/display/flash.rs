@@ -0,0 +1,120 @@
+use agb::fixnum::Vector2D;
+
+use crate::sfx::Side;
+
+use super::{ENEMY_X, PLAYER_X, SHIP_Y};
+
+/// How long a ship stays lit up for after getting hit
+const FLASH_FRAMES: u32 = 6;
+/// How bright a ship starts off, out of 16 which would be white
+const FLASH_BRIGHTNESS: u32 = 12;
+const SHIP_SIZE: i32 = 64;
+
+const DISPLAY_CONTROL: *mut u16 = 0x0400_0000 as *mut u16;
+/// Window 0 goes around the player's ship and window 1 around the enemy's
+const WINDOW_HORIZONTAL: [*mut u16; 2] = [0x0400_0040 as *mut u16, 0x0400_0042 as *mut u16];
+const WINDOW_VERTICAL: [*mut u16; 2] = [0x0400_0044 as *mut u16, 0x0400_0046 as *mut u16];
+const WINDOW_INSIDE: *mut u16 = 0x0400_0048 as *mut u16;
+const WINDOW_OUTSIDE: *mut u16 = 0x0400_004A as *mut u16;
+const BLEND_CONTROL: *mut u16 = 0x0400_0050 as *mut u16;
+const BRIGHTNESS: *mut u16 = 0x0400_0054 as *mut u16;
+
+/// Display control bits which turn on windows 0 and 1
+const WINDOW_ENABLE_BITS: [u16; 2] = [1 << 13, 1 << 14];
+/// Every background and sprites show in and out of the windows, but only inside them do
+/// colour effects apply
+const INSIDE_WINDOW_LAYERS: u16 = 0x3f3f;
+const OUTSIDE_WINDOW_LAYERS: u16 = 0x001f;
+/// Brightens sprites and nothing else
+const BRIGHTEN_SPRITES: u16 = 1 << 4 | 2 << 6;
+
+/// Lights up a ship for a moment when it gets hit. The ships share their palette with the rest of
+/// the battle sprites, which would all light up along with them, so the display brightens the
+/// colours in a window around the ship instead.
+pub(super) struct HitFlash {
+    enabled: bool,
+    /// For the player and then the enemy
+    frames_left: [u32; 2],
+}
+
+impl HitFlash {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            frames_left: [0; 2],
+        }
+    }
+
+    pub fn add(&mut self, side: Side) {
+        if !self.enabled {
+            return;
+        }
+
+        self.frames_left[side_index(side)] = FLASH_FRAMES;
+    }
+
+    /// Should be called every frame with how far the screen is shaken, so the windows stay on
+    /// the ships
+    pub fn update(&mut self, offset: Vector2D<i32>) {
+        let mut windows = 0;
+        let mut brightness = 0;
+
+        for (i, frames_left) in self.frames_left.iter_mut().enumerate() {
+            if *frames_left == 0 {
+                continue;
+            }
+
+            let x = [PLAYER_X, ENEMY_X][i] as i32 + offset.x;
+            let y = SHIP_Y as i32 + offset.y;
+
+            // Safety: these are the window registers, and only this uses them
+            unsafe {
+                WINDOW_HORIZONTAL[i].write_volatile(window_span(x, 240));
+                WINDOW_VERTICAL[i].write_volatile(window_span(y, 160));
+            }
+
+            windows |= WINDOW_ENABLE_BITS[i];
+            // fades out
+            brightness = brightness.max(FLASH_BRIGHTNESS * *frames_left / FLASH_FRAMES);
+            *frames_left -= 1;
+        }
+
+        // Safety: only the window enable bits of the display control get changed, and nothing else
+        // uses the window or colour effect registers
+        unsafe {
+            let display_control = DISPLAY_CONTROL.read_volatile();
+            DISPLAY_CONTROL.write_volatile(
+                display_control & !(WINDOW_ENABLE_BITS[0] | WINDOW_ENABLE_BITS[1]) | windows,
+            );
+
+            WINDOW_INSIDE.write_volatile(INSIDE_WINDOW_LAYERS);
+            WINDOW_OUTSIDE.write_volatile(OUTSIDE_WINDOW_LAYERS);
+            BLEND_CONTROL.write_volatile(if windows == 0 { 0 } else { BRIGHTEN_SPRITES });
+            BRIGHTNESS.write_volatile(brightness as u16);
+        }
+    }
+}
+
+impl Drop for HitFlash {
+    fn drop(&mut self) {
+        // turns off the windows and the effect, so they don't carry on past the battle
+        self.frames_left = [0; 2];
+        self.update((0, 0).into());
+    }
+}
+
+fn side_index(side: Side) -> usize {
+    match side {
+        Side::Player => 0,
+        Side::Enemy => 1,
+    }
+}
+
+/// A ship's worth of the screen from `start`, in the form the window registers take, which is the
+/// first pixel in the top byte and one past the last in the bottom byte
+fn window_span(start: i32, screen_size: i32) -> u16 {
+    let first = start.clamp(0, screen_size);
+    let end = (start + SHIP_SIZE).clamp(0, screen_size);
+
+    (first as u16) << 8 | end as u16
+}
@@ -0,0 +1,71 @@
+use agb::display::object::ObjectController;
+use agb::fixnum::Vector2D;
+
+use crate::graphics::{Alignment, NumberDisplay, TextDisplay};
+use crate::Ship;
+
+/// Along the very bottom of the screen, below the dice and well clear of the help text
+const HUD_Y: i32 = 152;
+
+/// A strip of information about the run and the current battle
+pub(super) struct BattleHud<'a> {
+    level_text: TextDisplay<'a>,
+    level: NumberDisplay<'a>,
+    enemy_name: TextDisplay<'a>,
+    rerolls_text: TextDisplay<'a>,
+    rerolls: NumberDisplay<'a>,
+    accepts_text: TextDisplay<'a>,
+    accepts: NumberDisplay<'a>,
+}
+
+fn enemy_name(ship: Ship) -> &'static str {
+    match ship {
+        Ship::Drone => "drone",
+        Ship::PilotedShip => "piloted ship",
+        Ship::Player | Ship::Shield => unreachable!(),
+    }
+}
+
+impl<'a> BattleHud<'a> {
+    pub fn new(level: u32, enemy: Ship, obj: &'a ObjectController) -> Self {
+        let origin = (0, 0).into();
+
+        let mut hud = Self {
+            level_text: TextDisplay::new(origin),
+            level: NumberDisplay::new(origin).aligned(Alignment::Left),
+            enemy_name: TextDisplay::new(origin),
+            rerolls_text: TextDisplay::new(origin),
+            rerolls: NumberDisplay::new(origin),
+            accepts_text: TextDisplay::new(origin),
+            accepts: NumberDisplay::new(origin),
+        };
+
+        hud.level_text.set_text("lv", obj);
+        hud.level.set_value(Some(level), obj);
+        hud.enemy_name.set_text(enemy_name(enemy), obj);
+        hud.rerolls_text.set_text("rolls", obj);
+        hud.accepts_text.set_text("accepts", obj);
+
+        hud.set_offset(origin);
+        hud
+    }
+
+    /// Moves everything over by `offset` from where it normally goes, to shake the screen
+    pub fn set_offset(&mut self, offset: Vector2D<i32>) {
+        let at = |x: i32| offset + (x, HUD_Y).into();
+
+        self.level_text.set_position(at(4));
+        // leaves room for three digits before the name
+        self.level.set_position(at(14));
+        self.enemy_name.set_position(at(36));
+        self.rerolls_text.set_position(at(150));
+        self.rerolls.set_position(at(182));
+        self.accepts_text.set_position(at(192));
+        self.accepts.set_position(at(232));
+    }
+
+    pub fn update(&mut self, rerolls: u32, accepts: u32, obj: &'a ObjectController) {
+        self.rerolls.set_value(Some(rerolls), obj);
+        self.accepts.set_value(Some(accepts), obj);
+    }
+}
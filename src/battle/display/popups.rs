@@ -0,0 +1,203 @@
+use agb::display::object::ObjectController;
+use agb::fixnum::Vector2D;
+use alloc::vec::Vec;
+
+use crate::battle::{NumberPopup, PopupKind};
+use crate::graphics::{NumberColour, NumberDisplay};
+use crate::sfx::Side;
+
+const POPUP_FRAMES: u32 = 48;
+/// Popups flicker for this many frames before they disappear
+const FADE_FRAMES: u32 = 16;
+/// How many frames it takes a popup to drift up by a pixel
+const FRAMES_PER_PIXEL: u32 = 4;
+/// Hits of the same kind on the same ship this close together get added up into one number
+const MERGE_FRAMES: u32 = 10;
+/// Popups which spawn while others are still this young get moved to the side to not overlap
+const CROWDED_FRAMES: u32 = 16;
+const CROWDED_OFFSETS: [i32; 3] = [0, -10, 10];
+/// Each one is at most a few sprites, but there are only 128 to go around
+const MAX_POPUPS: usize = 8;
+
+struct Popup<'a> {
+    side: Side,
+    kind: PopupKind,
+    amount: u32,
+    start: Vector2D<i32>,
+    age: u32,
+    display: NumberDisplay<'a>,
+}
+
+impl<'a> Popup<'a> {
+    fn position(&self) -> Vector2D<i32> {
+        self.start - (0, (self.age / FRAMES_PER_PIXEL) as i32).into()
+    }
+}
+
+/// Damage, heal and blocked amounts which float up from the ships and then fade out
+pub(super) struct NumberPopups<'a> {
+    popups: Vec<Popup<'a>>,
+    /// Added to every popup's position, to shake the screen
+    offset: Vector2D<i32>,
+}
+
+impl<'a> Default for NumberPopups<'a> {
+    fn default() -> Self {
+        Self {
+            popups: Vec::new(),
+            offset: (0, 0).into(),
+        }
+    }
+}
+
+impl<'a> NumberPopups<'a> {
+    pub fn add(&mut self, popup: NumberPopup, obj: &'a ObjectController) {
+        if let Some(existing) = self.popups.iter_mut().find(|existing| {
+            existing.side == popup.side
+                && existing.kind == popup.kind
+                && existing.age < MERGE_FRAMES
+        }) {
+            existing.amount += popup.amount;
+            existing.display.set_value(Some(existing.amount), obj);
+            existing
+                .display
+                .set_position(existing.position() + self.offset);
+            return;
+        }
+
+        if self.popups.len() >= MAX_POPUPS {
+            self.popups.remove(0);
+        }
+
+        let crowd = self
+            .popups
+            .iter()
+            .filter(|existing| existing.side == popup.side && existing.age < CROWDED_FRAMES)
+            .count();
+
+        let centre_x = match popup.side {
+            Side::Player => 30,
+            Side::Enemy => 185,
+        };
+        let start = (
+            centre_x + CROWDED_OFFSETS[crowd % CROWDED_OFFSETS.len()],
+            30,
+        )
+            .into();
+
+        let colour = match popup.kind {
+            PopupKind::Damage => NumberColour::Red,
+            PopupKind::Heal => NumberColour::Green,
+            PopupKind::Blocked => NumberColour::Blue,
+        };
+
+        let mut display = NumberDisplay::new_coloured(start + self.offset, colour);
+        display.set_value(Some(popup.amount), obj);
+
+        self.popups.push(Popup {
+            side: popup.side,
+            kind: popup.kind,
+            amount: popup.amount,
+            start,
+            age: 0,
+            display,
+        });
+    }
+
+    pub fn update(&mut self) {
+        for popup in self.popups.iter_mut() {
+            popup.age += 1;
+
+            let position = popup.position() + self.offset;
+            popup.display.set_position(position);
+
+            if popup.age + FADE_FRAMES >= POPUP_FRAMES && popup.age % 2 == 0 {
+                popup.display.hide();
+            } else {
+                popup.display.show();
+            }
+        }
+
+        self.popups.retain(|popup| popup.age < POPUP_FRAMES);
+    }
+
+    pub fn set_offset(&mut self, offset: Vector2D<i32>) {
+        self.offset = offset;
+
+        for popup in self.popups.iter_mut() {
+            let position = popup.position() + offset;
+            popup.display.set_position(position);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn popup(side: Side, kind: PopupKind, amount: u32) -> NumberPopup {
+        NumberPopup { side, kind, amount }
+    }
+
+    fn shown(popups: &NumberPopups) -> Vec<(Side, PopupKind, u32)> {
+        popups
+            .popups
+            .iter()
+            .map(|popup| (popup.side, popup.kind, popup.amount))
+            .collect()
+    }
+
+    #[test_case]
+    fn hits_close_together_add_up(gba: &mut agb::Gba) {
+        let obj = gba.display.object.get();
+        let mut popups = NumberPopups::default();
+
+        popups.add(popup(Side::Enemy, PopupKind::Damage, 3), &obj);
+        for _ in 1..MERGE_FRAMES {
+            popups.update();
+        }
+        popups.add(popup(Side::Enemy, PopupKind::Damage, 4), &obj);
+
+        assert_eq!(shown(&popups), [(Side::Enemy, PopupKind::Damage, 7)]);
+    }
+
+    #[test_case]
+    fn hits_further_apart_get_their_own_numbers(gba: &mut agb::Gba) {
+        let obj = gba.display.object.get();
+        let mut popups = NumberPopups::default();
+
+        popups.add(popup(Side::Enemy, PopupKind::Damage, 3), &obj);
+        for _ in 0..MERGE_FRAMES {
+            popups.update();
+        }
+        popups.add(popup(Side::Enemy, PopupKind::Damage, 4), &obj);
+
+        assert_eq!(
+            shown(&popups),
+            [
+                (Side::Enemy, PopupKind::Damage, 3),
+                (Side::Enemy, PopupKind::Damage, 4)
+            ]
+        );
+    }
+
+    #[test_case]
+    fn only_the_same_kind_on_the_same_ship_adds_up(gba: &mut agb::Gba) {
+        let obj = gba.display.object.get();
+        let mut popups = NumberPopups::default();
+
+        popups.add(popup(Side::Enemy, PopupKind::Damage, 3), &obj);
+        popups.add(popup(Side::Player, PopupKind::Damage, 4), &obj);
+        popups.add(popup(Side::Enemy, PopupKind::Blocked, 5), &obj);
+        popups.add(popup(Side::Enemy, PopupKind::Damage, 6), &obj);
+
+        assert_eq!(
+            shown(&popups),
+            [
+                (Side::Enemy, PopupKind::Damage, 9),
+                (Side::Player, PopupKind::Damage, 4),
+                (Side::Enemy, PopupKind::Blocked, 5)
+            ]
+        );
+    }
+}
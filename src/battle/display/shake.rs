@@ -0,0 +1,62 @@
+use agb::fixnum::Vector2D;
+use agb::rng;
+
+/// How far the screen moves at most, however big the hit
+const MAX_SHAKE_STRENGTH: i32 = 6;
+const MIN_SHAKE_FRAMES: u32 = 8;
+const MAX_SHAKE_FRAMES: u32 = 30;
+
+/// Jolts the battle around when a ship takes a hit, harder and for longer the more damage it was
+pub(super) struct ScreenShake {
+    enabled: bool,
+    strength: i32,
+    frames_left: u32,
+    duration: u32,
+    offset: Vector2D<i32>,
+}
+
+impl ScreenShake {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            strength: 0,
+            frames_left: 0,
+            duration: 0,
+            offset: (0, 0).into(),
+        }
+    }
+
+    pub fn add(&mut self, damage: u32) {
+        if !self.enabled {
+            return;
+        }
+
+        let strength = (1 + damage as i32 / 3).min(MAX_SHAKE_STRENGTH);
+        let frames = (MIN_SHAKE_FRAMES + damage * 2).min(MAX_SHAKE_FRAMES);
+
+        // a small hit on top of a big one shouldn't cut the big one short
+        if self.frames_left == 0 || strength >= self.strength {
+            self.strength = strength;
+        }
+        self.frames_left = self.frames_left.max(frames);
+        self.duration = self.frames_left;
+    }
+
+    pub fn update(&mut self) {
+        if self.frames_left == 0 {
+            self.offset = (0, 0).into();
+            return;
+        }
+
+        // dies down towards the end
+        let amplitude = (self.strength * self.frames_left as i32 / self.duration as i32).max(1);
+        let mut jolt = || rng::gen().rem_euclid(amplitude * 2 + 1) - amplitude;
+        self.offset = (jolt(), jolt()).into();
+
+        self.frames_left -= 1;
+    }
+
+    pub fn offset(&self) -> Vector2D<i32> {
+        self.offset
+    }
+}
@@ -1,18 +1,22 @@
 use agb::{
     display::{
-        object::{Object, ObjectController},
+        object::{ObjectController, Sprite},
         palette16::Palette16,
-        tiled::{RegularMap, TileSet, TileSetting},
+        tiled::{RegularMap, TileSet, TileSetting, VRamManager},
         HEIGHT, WIDTH,
     },
+    fixnum::Vector2D,
     include_gfx,
-    input::{Button, Tri},
+    input::Tri,
 };
 
 use alloc::vec::Vec;
 
 use crate::{
-    graphics::{FACE_SPRITES, MODIFIED_BOX, SELECTED_BOX, SELECT_BOX},
+    graphics::{FACE_SPRITES, MODIFIED_BOX, SELECTED_BOX},
+    progress::achievements::AchievementToast,
+    sfx::SoundEvent,
+    ui::{CursorStyle, Focus, FocusEvent, FocusSounds, Icons, Navigation},
     Agb, Die, Face, PlayerDice,
 };
 
@@ -97,45 +101,45 @@ fn move_net_position_ud(idx: usize, direction: Tri) -> usize {
     }
 }
 
-fn create_dice_display<'a>(gfx: &'a ObjectController, dice: &'_ PlayerDice) -> Vec<Object<'a>> {
-    let mut objects = Vec::new();
-    for (idx, dice) in dice.dice.iter().enumerate() {
-        let mut obj = gfx.object(gfx.sprite(FACE_SPRITES.sprite_for_face(dice.faces[1])));
-        obj.set_x((idx as i32 * 32 - 24 / 2 + 20) as u16);
-        obj.set_y(16 - 24 / 2);
-
-        obj.show();
-
-        objects.push(obj);
-    }
-    objects
+fn dice_icons<'a>(
+    dice: &'a PlayerDice,
+) -> impl Iterator<Item = (Vector2D<i32>, &'static Sprite)> + 'a {
+    dice.dice.iter().enumerate().map(|(idx, die)| {
+        (
+            (idx as i32 * 32 - 24 / 2 + 20, 16 - 24 / 2).into(),
+            FACE_SPRITES.sprite_for_face(die.faces[1]),
+        )
+    })
 }
 
-fn create_net<'a>(gfx: &'a ObjectController, die: &'_ Die, modified: &[usize]) -> Vec<Object<'a>> {
-    let mut objects = Vec::new();
-    for (idx, &face) in die.faces.iter().enumerate() {
-        let mut obj = gfx.object(gfx.sprite(FACE_SPRITES.sprite_for_face(face)));
-        let (x, y) = screen_position_for_index(idx);
-        obj.set_x((x - 24 / 2) as u16);
-        obj.set_y((y - 24 / 2) as u16);
-
-        obj.show();
-
-        objects.push(obj);
-    }
-
-    for &m in modified.iter().chain(core::iter::once(&3)) {
-        let mut obj = gfx.object(gfx.sprite(MODIFIED_BOX));
-        let (x, y) = screen_position_for_index(m);
-        obj.set_x((x - 32 / 2) as u16);
-        obj.set_y((y - 32 / 2) as u16);
-
-        obj.show();
+/// The faces of a die folded out flat, with a box around the ones which have been changed
+#[derive(Default)]
+struct NetDisplay<'a> {
+    faces: Icons<'a>,
+    modified: Icons<'a>,
+}
 
-        objects.push(obj);
+impl<'a> NetDisplay<'a> {
+    pub fn set(&mut self, die: &Die, modified: &[usize], obj: &'a ObjectController) {
+        self.faces.set(
+            die.faces.iter().enumerate().map(|(idx, &face)| {
+                let (x, y) = screen_position_for_index(idx);
+                (
+                    (x as i32 - 24 / 2, y as i32 - 24 / 2).into(),
+                    FACE_SPRITES.sprite_for_face(face),
+                )
+            }),
+            obj,
+        );
+
+        self.modified.set(
+            modified.iter().chain(core::iter::once(&3)).map(|&m| {
+                let (x, y) = screen_position_for_index(m);
+                ((x as i32 - 32 / 2, y as i32 - 32 / 2).into(), MODIFIED_BOX)
+            }),
+            obj,
+        );
     }
-
-    objects
 }
 
 fn upgrade_position(idx: usize) -> (u32, u32) {
@@ -145,19 +149,65 @@ fn upgrade_position(idx: usize) -> (u32, u32) {
     )
 }
 
-fn create_upgrade_objects<'a>(gfx: &'a ObjectController, upgrades: &[Face]) -> Vec<Object<'a>> {
-    let mut objects = Vec::new();
-    for (idx, &upgrade) in upgrades.iter().enumerate() {
-        let mut obj = gfx.object(gfx.sprite(FACE_SPRITES.sprite_for_face(upgrade)));
+fn upgrade_icons(upgrades: &[Face]) -> impl Iterator<Item = (Vector2D<i32>, &'static Sprite)> + '_ {
+    upgrades.iter().enumerate().map(|(idx, &upgrade)| {
         let (x, y) = upgrade_position(idx);
-        obj.set_x((x - 24 / 2) as u16);
-        obj.set_y((y - 24 / 2) as u16);
+        (
+            (x as i32 - 24 / 2, y as i32 - 24 / 2).into(),
+            FACE_SPRITES.sprite_for_face(upgrade),
+        )
+    })
+}
 
-        obj.show();
+/// Where the select box goes to surround each of the `count` items
+fn select_box_positions(
+    count: usize,
+    position_for_index: fn(usize) -> (u32, u32),
+) -> Vec<Vector2D<i32>> {
+    (0..count)
+        .map(|idx| {
+            let (x, y) = position_for_index(idx);
+            (x as i32 - 32 / 2, y as i32 - 32 / 2).into()
+        })
+        .collect()
+}
 
-        objects.push(obj);
+fn dice_position(idx: usize) -> (u32, u32) {
+    (idx as u32 * 32 + 20, 16)
+}
+
+fn modified_faces(modified: &[Cursor], dice: usize) -> Vec<usize> {
+    modified
+        .iter()
+        .filter_map(|x| (x.dice == dice).then_some(x.face))
+        .collect()
+}
+
+fn load_description(
+    descriptions_map: &mut RegularMap,
+    vram: &mut VRamManager,
+    face: Face,
+    tilesets: &[TileSet; 2],
+) {
+    for y in 0..11 {
+        for x in 0..8 {
+            if (face as usize) < 10 {
+                descriptions_map.set_tile(
+                    vram,
+                    (x, y).into(),
+                    &tilesets[0],
+                    TileSetting::new(y * 8 + x + 8 * 11 * face as u16, false, false, 1),
+                )
+            } else {
+                descriptions_map.set_tile(
+                    vram,
+                    (x, y).into(),
+                    &tilesets[1],
+                    TileSetting::new(y * 8 + x + 8 * 11 * (face as u16 - 10), false, false, 2),
+                )
+            }
+        }
     }
-    objects
 }
 
 pub(crate) fn customise_screen(
@@ -174,212 +224,220 @@ pub(crate) fn customise_screen(
     help_background.set_scroll_pos((u16::MAX - 148, u16::MAX - 34).into());
     crate::background::load_help_text(&mut agb.vram, help_background, 0, (0, 0));
 
-    let descriptions_1_tileset = TileSet::new(
-        descriptions::descriptions1.tiles,
-        agb::display::tiled::TileFormat::FourBpp,
-    );
-    let descriptions_2_tileset = TileSet::new(
-        descriptions::descriptions2.tiles,
-        agb::display::tiled::TileFormat::FourBpp,
-    );
+    let descriptions_tilesets = [
+        TileSet::new(
+            descriptions::descriptions1.tiles,
+            agb::display::tiled::TileFormat::FourBpp,
+        ),
+        TileSet::new(
+            descriptions::descriptions2.tiles,
+            agb::display::tiled::TileFormat::FourBpp,
+        ),
+    ];
+
+    let obj = &agb.obj;
 
     // create the dice
 
-    let mut _net = create_net(&agb.obj, &player_dice.dice[0], &[]);
-    let mut _dice = create_dice_display(&agb.obj, &player_dice);
+    let mut net = NetDisplay::default();
+    net.set(&player_dice.dice[0], &[], obj);
+    let mut dice_display = Icons::default();
+    dice_display.set(dice_icons(&player_dice), obj);
 
     agb.sfx.frame();
 
-    let mut upgrades = crate::level_generation::generate_upgrades(level);
-    let mut _upgrade_objects = create_upgrade_objects(&agb.obj, &upgrades);
+    let mut upgrades =
+        crate::level_generation::generate_upgrades(level, &agb.progress.face_unlocks);
+    let mut upgrade_objects = Icons::default();
+    upgrade_objects.set(upgrade_icons(&upgrades), obj);
 
     let mut input = agb::input::ButtonController::new();
 
-    let mut select_box = agb.obj.object(agb.obj.sprite(SELECT_BOX.sprite(0)));
+    let sounds = FocusSounds {
+        moved: Some(SoundEvent::MoveCursor),
+        selected: None,
+        back: Some(SoundEvent::Back),
+    };
 
-    select_box.show();
+    // there's nothing to go back to from picking a die
+    let mut dice_focus = Focus::new(
+        Navigation::Row,
+        select_box_positions(player_dice.dice.len(), dice_position),
+        CursorStyle::SelectBox,
+        FocusSounds {
+            back: None,
+            ..sounds
+        },
+        obj,
+    );
+    let mut face_focus = Focus::new(
+        Navigation::Custom {
+            lr: move_net_position_lr,
+            ud: move_net_position_ud,
+        },
+        select_box_positions(6, screen_position_for_index),
+        CursorStyle::SelectBox,
+        sounds,
+        obj,
+    );
+    face_focus.set_index(1);
+    face_focus.hide();
+    let mut upgrade_focus = Focus::new(
+        Navigation::Column,
+        select_box_positions(upgrades.len(), upgrade_position),
+        CursorStyle::SelectBox,
+        sounds,
+        obj,
+    );
+    upgrade_focus.hide();
 
-    let mut selected_dice = agb.obj.object(agb.obj.sprite(SELECTED_BOX));
+    let mut selected_dice = obj.object(obj.sprite(SELECTED_BOX));
     selected_dice.hide();
-    let mut selected_face = agb.obj.object(agb.obj.sprite(SELECTED_BOX));
+    let mut selected_face = obj.object(obj.sprite(SELECTED_BOX));
     selected_face.hide();
-    agb.sfx.frame();
 
-    let mut counter = 0usize;
+    let mut achievement_toast = AchievementToast::new((88, 4).into());
+    agb.sfx.frame();
 
     let mut state = CustomiseState::Dice;
 
-    let mut cursor = Cursor {
-        dice: 0,
-        face: 1,
-        upgrade: 0,
-    };
+    // the upgrade whose description is on screen
+    let mut description_shown = None;
 
     let mut modified: Vec<Cursor> = Vec::new();
 
     loop {
-        counter = counter.wrapping_add(1);
+        agb.run_frames = agb.run_frames.saturating_add(1);
         input.update();
-        let ud = (
-            input.is_just_pressed(Button::UP),
-            input.is_just_pressed(Button::DOWN),
-        )
-            .into();
-        let lr = (
-            input.is_just_pressed(Button::LEFT),
-            input.is_just_pressed(Button::RIGHT),
-        )
-            .into();
 
-        if ud != Tri::Zero || lr != Tri::Zero {
-            agb.sfx.move_cursor();
-        }
-
-        match &mut state {
-            CustomiseState::Dice => {
-                selected_dice.hide();
-                let new_dice = (cursor.dice as isize + lr as isize)
-                    .rem_euclid(player_dice.dice.len() as isize)
-                    as usize;
-                if new_dice != cursor.dice {
-                    cursor.dice = new_dice;
-                    _net = create_net(
-                        &agb.obj,
-                        &player_dice.dice[cursor.dice],
-                        &modified
-                            .iter()
-                            .filter_map(|x| (x.dice == cursor.dice).then_some(x.face))
-                            .collect::<Vec<usize>>(),
+        match state {
+            CustomiseState::Dice => match dice_focus.update(&input, &mut agb.sfx, obj) {
+                Some(FocusEvent::Moved) => {
+                    let dice = dice_focus.index();
+                    net.set(
+                        &player_dice.dice[dice],
+                        &modified_faces(&modified, dice),
+                        obj,
                     );
                 }
+                Some(FocusEvent::Selected(dice)) => {
+                    // the cursor can move onto the die on the same frame it gets picked
+                    net.set(
+                        &player_dice.dice[dice],
+                        &modified_faces(&modified, dice),
+                        obj,
+                    );
 
-                select_box.set_x((cursor.dice as i32 * 32 - 32 / 2 + 20) as u16);
-                select_box.set_y(0);
-
-                if input.is_just_pressed(Button::A) {
-                    selected_dice.set_x((cursor.dice as i32 * 32 - 32 / 2 + 20) as u16);
-                    selected_dice.set_y(0);
-                    selected_dice.show();
+                    let (x, y) = dice_position(dice);
+                    selected_dice
+                        .set_position((x as i32 - 32 / 2, y as i32 - 32 / 2).into())
+                        .show();
+                    dice_focus.hide();
+                    face_focus.show();
                     state = CustomiseState::Face;
-                    agb.sfx.select();
+                    agb.sfx.play(SoundEvent::Select);
                 }
-            }
-            CustomiseState::Face => {
-                cursor.face = move_net_position_lr(cursor.face, lr);
-                cursor.face = move_net_position_ud(cursor.face, ud);
-
-                let (x, y) = screen_position_for_index(cursor.face);
-                select_box.set_x((x - 32 / 2) as u16);
-                select_box.set_y((y - 32 / 2) as u16);
-                selected_face.hide();
-
-                if input.is_just_pressed(Button::B) {
+                _ => {}
+            },
+            CustomiseState::Face => match face_focus.update(&input, &mut agb.sfx, obj) {
+                Some(FocusEvent::Back) => {
+                    selected_dice.hide();
+                    face_focus.hide();
+                    dice_focus.show();
                     state = CustomiseState::Dice;
-                    agb.sfx.back();
-                } else if input.is_just_pressed(Button::A)
-                    && !upgrades.is_empty()
-                    && !modified.contains(&Cursor {
-                        dice: cursor.dice,
-                        face: cursor.face,
-                        upgrade: 0,
-                    })
+                }
+                Some(FocusEvent::Selected(face))
+                    if !upgrades.is_empty()
+                        && !modified.contains(&Cursor {
+                            dice: dice_focus.index(),
+                            face,
+                            upgrade: 0,
+                        }) =>
                 {
-                    selected_face.set_x((x - 32 / 2) as u16);
-                    selected_face.set_y((y - 32 / 2) as u16);
-                    selected_face.show();
-
-                    cursor.upgrade += upgrades.len();
+                    let (x, y) = screen_position_for_index(face);
+                    selected_face
+                        .set_position((x as i32 - 32 / 2, y as i32 - 32 / 2).into())
+                        .show();
+                    face_focus.hide();
+                    upgrade_focus.show();
 
                     state = CustomiseState::Upgrade;
-                    agb.sfx.select();
+                    agb.sfx.play(SoundEvent::Select);
                 }
-            }
+                _ => {}
+            },
             CustomiseState::Upgrade => {
-                let old_updade = cursor.upgrade;
-                cursor.upgrade = (cursor.upgrade as isize + ud as isize)
-                    .rem_euclid(upgrades.len() as isize) as usize;
-
-                if (upgrades[cursor.upgrade] as u32) < 17 {
-                    if cursor.upgrade != old_updade {
-                        for y in 0..11 {
-                            for x in 0..8 {
-                                if (upgrades[cursor.upgrade] as usize) < 10 {
-                                    descriptions_map.set_tile(
-                                        &mut agb.vram,
-                                        (x, y).into(),
-                                        &descriptions_1_tileset,
-                                        TileSetting::new(
-                                            y * 8 + x + 8 * 11 * upgrades[cursor.upgrade] as u16,
-                                            false,
-                                            false,
-                                            1,
-                                        ),
-                                    )
-                                } else {
-                                    descriptions_map.set_tile(
-                                        &mut agb.vram,
-                                        (x, y).into(),
-                                        &descriptions_2_tileset,
-                                        TileSetting::new(
-                                            y * 8
-                                                + x
-                                                + 8 * 11 * (upgrades[cursor.upgrade] as u16 - 10),
-                                            false,
-                                            false,
-                                            2,
-                                        ),
-                                    )
-                                }
-                            }
-                        }
+                let event = upgrade_focus.update(&input, &mut agb.sfx, obj);
+                let dice = dice_focus.index();
+                let face = face_focus.index();
+
+                match event {
+                    Some(FocusEvent::Back) => {
+                        selected_face.hide();
+                        upgrade_focus.hide();
+                        face_focus.show();
+                        state = CustomiseState::Face;
                     }
-                    descriptions_map.show();
-                } else {
-                    descriptions_map.hide();
+                    Some(FocusEvent::Selected(upgrade))
+                        if player_dice.dice[dice].faces[face] != upgrades[upgrade] =>
+                    {
+                        modified.push(Cursor {
+                            dice,
+                            face,
+                            upgrade: 0,
+                        });
+
+                        player_dice.dice[dice].faces[face] = upgrades[upgrade];
+                        upgrades.remove(upgrade);
+                        upgrade_objects.set(upgrade_icons(&upgrades), obj);
+                        upgrade_focus
+                            .set_positions(select_box_positions(upgrades.len(), upgrade_position));
+
+                        net.set(
+                            &player_dice.dice[dice],
+                            &modified_faces(&modified, dice),
+                            obj,
+                        );
+                        dice_display.set(dice_icons(&player_dice), obj);
+
+                        selected_face.hide();
+                        upgrade_focus.hide();
+                        face_focus.show();
+                        state = CustomiseState::Face;
+                        agb.sfx.play(SoundEvent::Accept);
+                    }
+                    _ => {}
                 }
+            }
+        }
 
-                let (x, y) = upgrade_position(cursor.upgrade);
-                select_box.set_x((x - 32 / 2) as u16);
-                select_box.set_y((y - 32 / 2) as u16);
-
-                if input.is_just_pressed(Button::B) {
-                    state = CustomiseState::Face;
-                    agb.sfx.back();
-                } else if input.is_just_pressed(Button::A)
-                    && player_dice.dice[cursor.dice].faces[cursor.face] != upgrades[cursor.upgrade]
-                {
-                    descriptions_map.hide();
-
-                    modified.push(Cursor {
-                        dice: cursor.dice,
-                        face: cursor.face,
-                        upgrade: 0,
-                    });
-
-                    player_dice.dice[cursor.dice].faces[cursor.face] = upgrades[cursor.upgrade];
-                    upgrades.remove(cursor.upgrade);
-                    _upgrade_objects = create_upgrade_objects(&agb.obj, &upgrades);
-
-                    _net = create_net(
-                        &agb.obj,
-                        &player_dice.dice[cursor.dice],
-                        &modified
-                            .iter()
-                            .filter_map(|x| (x.dice == cursor.dice).then_some(x.face))
-                            .collect::<Vec<usize>>(),
+        let upgrade = match state {
+            CustomiseState::Upgrade => upgrades.get(upgrade_focus.index()).copied(),
+            _ => None,
+        };
+
+        if upgrade != description_shown {
+            match upgrade {
+                Some(face) if (face as u32) < 17 => {
+                    load_description(
+                        descriptions_map,
+                        &mut agb.vram,
+                        face,
+                        &descriptions_tilesets,
                     );
-                    _dice = create_dice_display(&agb.obj, &player_dice);
-                    state = CustomiseState::Face;
-                    agb.sfx.accept();
+                    descriptions_map.show();
                 }
+                _ => descriptions_map.hide(),
             }
+
+            description_shown = upgrade;
         }
 
         if upgrades.is_empty() {
             break;
         }
 
-        select_box.set_sprite(agb.obj.sprite(SELECT_BOX.animation_sprite(counter / 10)));
+        achievement_toast.update(&mut agb.progress.achievements, obj);
 
         agb.star_background.update();
         let _ = agb::rng::gen();
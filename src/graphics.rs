@@ -9,7 +9,10 @@ use crate::{EnemyAttackType, Face, Ship};
 const SPRITES: &agb::display::object::Graphics = agb::include_aseprite!(
     "gfx/dice-faces.aseprite",
     "gfx/ships.aseprite",
-    "gfx/small-sprites.aseprite"
+    "gfx/small-sprites.aseprite",
+    "gfx/ui.aseprite",
+    "gfx/explosion.aseprite",
+    "gfx/banners.aseprite"
 );
 pub const FACE_SPRITES: &FaceSprites = &FaceSprites::load_face_sprites();
 pub const ENEMY_ATTACK_SPRITES: &EnemyAttackSprites = &EnemyAttackSprites::new();
@@ -21,11 +24,37 @@ pub const BULLET_SPRITE: &Sprite = SPRITES.tags().get("bullet").sprite(0);
 pub const DISRUPT_BULLET: &Sprite = SPRITES.tags().get("disrupt bullet").sprite(0);
 pub const BURST_BULLET: &Sprite = SPRITES.tags().get("burst shield bullet").sprite(0);
 pub const SHIELD: &Tag = SPRITES.tags().get("ship shield");
+pub const SHIELD_DEBRIS: &Tag = SPRITES.tags().get("shield debris");
+pub const HEAL_SPARKLE: &Tag = SPRITES.tags().get("heal sparkle");
+pub const EXPLOSION: &Tag = SPRITES.tags().get("explosion");
+pub const VICTORY_BANNER: &Tag = SPRITES.tags().get("victory banner");
+pub const DESTROYED_BANNER: &Tag = SPRITES.tags().get("destroyed banner");
+
+pub const LETTERS: &Tag = SPRITES.tags().get("letters");
+pub const PUNCTUATION: &Tag = SPRITES.tags().get("punctuation");
 
 pub const SHIP_SPRITES: &ShipSprites = &ShipSprites::load_ship_sprites();
 
 pub const SMALL_SPRITES: &SmallSprites = &SmallSprites {};
 
+/// The colours numbers can be drawn in. Anything other than `Normal` has a transparent
+/// background so it can go on top of other things.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NumberColour {
+    Normal,
+    Red,
+    Green,
+    Blue,
+}
+
+/// The colours bars can be drawn in
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BarColour {
+    Health,
+    Cooldown,
+    Shield,
+}
+
 pub struct FaceSprites {
     sprites: [&'static Sprite; 17],
 }
@@ -106,12 +135,64 @@ impl SmallSprites {
         SPRITES.tags().get("numbers").sprite(i as usize)
     }
 
+    pub const fn coloured_number(&self, i: u32, colour: NumberColour) -> &'static Sprite {
+        let tag = match colour {
+            NumberColour::Normal => "numbers",
+            NumberColour::Red => "red numbers",
+            NumberColour::Green => "green numbers",
+            NumberColour::Blue => "blue numbers",
+        };
+
+        SPRITES.tags().get(tag).sprite(i as usize)
+    }
+
     pub const fn slash(&self) -> &'static Sprite {
         SPRITES.tags().get("numbers").sprite(10)
     }
 
-    pub const fn red_bar(&self, i: usize) -> &'static Sprite {
-        SPRITES.tags().get("red bar").sprite(i)
+    /// A cell of a bar `width` pixels wide with `filled` pixels in the bar's colour, then `ghost`
+    /// pixels of recent damage and empty for the rest
+    pub const fn bar(
+        &self,
+        colour: BarColour,
+        width: usize,
+        filled: usize,
+        ghost: usize,
+    ) -> &'static Sprite {
+        let tag = match colour {
+            BarColour::Health => "health bar",
+            BarColour::Cooldown => "cooldown bar",
+            BarColour::Shield => "shield bar",
+        };
+
+        // cells are grouped by width starting from 1 pixel, and within a width every ghost length
+        // for one filled length comes before the next filled length
+        let width_start = width * (width + 1) * (width + 2) / 6 - 1;
+
+        SPRITES
+            .tags()
+            .get(tag)
+            .sprite(width_start + filled * (2 * width + 3 - filled) / 2 + ghost)
+    }
+
+    /// The sprite to use for a character of text, or `None` if it should be left blank
+    pub fn character(&self, c: char) -> Option<&'static Sprite> {
+        let punctuation = |i| Some(PUNCTUATION.sprite(i));
+
+        match c.to_ascii_uppercase() {
+            c @ 'A'..='Z' => Some(LETTERS.sprite(c as usize - 'A' as usize)),
+            c @ '0'..='9' => Some(self.number(c as u32 - '0' as u32)),
+            '/' => Some(self.slash()),
+            '-' => punctuation(0),
+            '!' => punctuation(1),
+            ':' => punctuation(2),
+            '.' => punctuation(3),
+            '?' => punctuation(4),
+            '+' => punctuation(5),
+            '_' => punctuation(6),
+            '>' => punctuation(7),
+            _ => None,
+        }
     }
 }
 
@@ -135,166 +216,476 @@ impl EnemyAttackSprites {
     }
 }
 
+/// How long the damage ghost of a health bar waits before it starts to drain
+const GHOST_DELAY_FRAMES: u32 = 30;
+/// How many frames it takes the damage ghost to drain by a pixel
+const GHOST_DRAIN_FRAMES: u32 = 2;
+
+/// The part of a bar which was lost recently, left behind for a bit so you can see how much went
+struct DamageGhost {
+    value: usize,
+    delay: u32,
+    frame: u32,
+}
+
 pub struct HealthBar<'a> {
     max: usize,
-    sprites: Vec<Object<'a>>,
+    value: usize,
+    colour: BarColour,
+    ghost: Option<DamageGhost>,
+    /// Each cell along with the filled and ghost pixels it is currently showing
+    cells: Vec<(Object<'a>, (usize, usize))>,
 }
 
 impl<'a> HealthBar<'a> {
     pub fn new(pos: Vector2D<i32>, max: usize, obj: &'a ObjectController) -> Self {
-        assert_eq!(max % 8, 0);
+        Self::new_coloured(pos, max, BarColour::Health, obj)
+    }
 
-        let sprites = (0..(max / 8))
-            .into_iter()
+    /// Bars can be any width. If it isn't a multiple of 8, the last cell is narrower than the
+    /// others.
+    pub fn new_coloured(
+        pos: Vector2D<i32>,
+        max: usize,
+        colour: BarColour,
+        obj: &'a ObjectController,
+    ) -> Self {
+        let cells = (0..(max + 7) / 8)
             .map(|i| {
-                let health_sprite = obj.sprite(SMALL_SPRITES.red_bar(0));
+                let width = cell_width(i, max);
+                let health_sprite = obj.sprite(SMALL_SPRITES.bar(colour, width, width, 0));
 
                 let mut health_object = obj.object(health_sprite);
                 health_object
-                    .set_position(pos + (i as i32 * 8, 0).into())
+                    .set_position(pos + ((i * 8) as i32, 0).into())
                     .show();
-                health_object
+                (health_object, (width, 0))
             })
             .collect();
 
-        Self { max, sprites }
+        Self {
+            max,
+            value: max,
+            colour,
+            ghost: None,
+            cells,
+        }
+    }
+
+    /// Leave what was lost behind for a moment before draining it away
+    pub fn enable_damage_ghost(&mut self) {
+        self.ghost = Some(DamageGhost {
+            value: self.value,
+            delay: 0,
+            frame: 0,
+        });
+    }
+
+    pub fn set_colour(&mut self, colour: BarColour, obj: &'a ObjectController) {
+        if self.colour != colour {
+            self.colour = colour;
+            self.redraw(true, obj);
+        }
     }
 
     pub fn set_value(&mut self, new_value: usize, obj: &'a ObjectController) {
         assert!(new_value <= self.max);
 
-        for (i, sprite) in self.sprites.iter_mut().enumerate() {
-            if (i + 1) * 8 < new_value {
-                sprite.set_sprite(obj.sprite(SMALL_SPRITES.red_bar(0)));
-            } else if i * 8 < new_value {
-                sprite.set_sprite(obj.sprite(SMALL_SPRITES.red_bar(8 - (new_value - i * 8))));
-            } else {
-                sprite.set_sprite(obj.sprite(SMALL_SPRITES.red_bar(8)));
+        if let Some(ghost) = &mut self.ghost {
+            if new_value < self.value {
+                ghost.value = ghost.value.max(self.value);
+                ghost.delay = GHOST_DELAY_FRAMES;
+            }
+
+            ghost.value = ghost.value.max(new_value);
+        }
+
+        self.value = new_value;
+        self.redraw(false, obj);
+    }
+
+    /// Drains the damage ghost, should be called once a frame if it is enabled
+    pub fn update(&mut self, obj: &'a ObjectController) {
+        if let Some(ghost) = &mut self.ghost {
+            if ghost.delay > 0 {
+                ghost.delay -= 1;
+            } else if ghost.value > self.value {
+                ghost.frame = ghost.frame.wrapping_add(1);
+                if ghost.frame % GHOST_DRAIN_FRAMES == 0 {
+                    ghost.value -= 1;
+                }
             }
         }
+
+        self.redraw(false, obj);
+    }
+
+    fn redraw(&mut self, force: bool, obj: &'a ObjectController) {
+        let ghost_value = self
+            .ghost
+            .as_ref()
+            .map_or(self.value, |ghost| ghost.value.max(self.value));
+
+        for (i, (cell, shown)) in self.cells.iter_mut().enumerate() {
+            let start = i * 8;
+            let width = cell_width(i, self.max);
+            let filled = self.value.saturating_sub(start).min(width);
+            let ghost = ghost_value.saturating_sub(start).min(width) - filled;
+
+            if force || *shown != (filled, ghost) {
+                cell.set_sprite(obj.sprite(SMALL_SPRITES.bar(self.colour, width, filled, ghost)));
+                *shown = (filled, ghost);
+            }
+        }
+    }
+
+    pub fn set_position(&mut self, pos: Vector2D<i32>) {
+        for (i, (cell, _)) in self.cells.iter_mut().enumerate() {
+            cell.set_position(pos + ((i * 8) as i32, 0).into());
+        }
     }
 
     pub fn show(&mut self) {
-        for obj in self.sprites.iter_mut() {
+        for (obj, _) in self.cells.iter_mut() {
             obj.show();
         }
     }
 
     pub fn hide(&mut self) {
-        for obj in self.sprites.iter_mut() {
+        for (obj, _) in self.cells.iter_mut() {
             obj.hide();
         }
     }
 }
 
+/// How many pixels wide the `i`th cell of a bar `max` pixels wide is
+fn cell_width(i: usize, max: usize) -> usize {
+    (max - i * 8).min(8)
+}
+
+/// Counting moves this fraction of the remaining distance at a time, so big changes don't take
+/// forever but small ones still tick one by one
+const COUNT_DIVISOR: u32 = 8;
+const COUNT_FRAMES_PER_STEP: u32 = 2;
+
+fn count_towards(shown: u32, target: u32) -> u32 {
+    if shown < target {
+        shown + ((target - shown) / COUNT_DIVISOR).max(1)
+    } else {
+        shown - ((shown - target) / COUNT_DIVISOR).max(1)
+    }
+}
+
 pub struct FractionDisplay<'a> {
-    sprites: Vec<Object<'a>>,
+    current: NumberDisplay<'a>,
+    max: NumberDisplay<'a>,
+    slash: Object<'a>,
     digits: usize,
-
-    current_current: usize,
-    current_max: usize,
 }
 
 impl<'a> FractionDisplay<'a> {
     pub fn new(pos: Vector2D<i32>, digits: usize, obj: &'a ObjectController) -> Self {
-        let mut sprites = Vec::with_capacity(digits * 2 + 1);
-
-        for i in 0..digits {
-            let mut left_digit = obj.object(obj.sprite(SMALL_SPRITES.number(0)));
-            left_digit.set_position(pos + (i as i32 * 4, 0).into());
-
-            sprites.push(left_digit);
-
-            let mut right_digit = obj.object(obj.sprite(SMALL_SPRITES.number(0)));
-            right_digit.set_position(pos + (i as i32 * 4 + digits as i32 * 4 + 7, 0).into());
+        let mut current = NumberDisplay::new(pos).padded(digits);
+        current.set_value(Some(0), obj);
+
+        let mut max = NumberDisplay::new(pos)
+            .aligned(Alignment::Left)
+            .padded(digits);
+        max.set_value(Some(0), obj);
+
+        let mut fraction = Self {
+            current,
+            max,
+            slash: obj.object(obj.sprite(SMALL_SPRITES.slash())),
+            digits,
+        };
 
-            sprites.push(right_digit);
-        }
+        fraction.set_position(pos);
+        fraction
+    }
 
-        let mut slash = obj.object(obj.sprite(SMALL_SPRITES.slash()));
-        slash.set_position(pos + (digits as i32 * 4 + 1, 0).into());
-        sprites.push(slash);
+    pub fn set_position(&mut self, pos: Vector2D<i32>) {
+        let digits = self.digits as i32;
 
-        Self {
-            sprites,
-            digits,
-            current_current: 0,
-            current_max: 0,
-        }
+        self.current
+            .set_position(pos + ((digits - 1) * 4, 0).into());
+        self.slash.set_position(pos + (digits * 4 + 1, 0).into());
+        self.max.set_position(pos + (digits * 4 + 7, 0).into());
     }
 
     pub fn set_value(&mut self, current: usize, max: usize, obj: &'a ObjectController) {
-        if self.current_current == current && self.current_max == max {
-            return;
-        }
-
-        let mut current = current;
-        let mut max = max;
+        self.current.set_value(Some(current as u32), obj);
+        self.max.set_value(Some(max as u32), obj);
+    }
 
-        for i in 0..self.digits {
-            let current_value_digit = current % 10;
-            current /= 10;
-            let current_value_sprite = &mut self.sprites[(self.digits - i) * 2 - 2];
-            current_value_sprite
-                .set_sprite(obj.sprite(SMALL_SPRITES.number(current_value_digit as u32)));
+    /// Like `set_value`, but the current value ticks towards `current` as `update` gets called
+    pub fn count_to(&mut self, current: usize, max: usize, obj: &'a ObjectController) {
+        self.current.count_to(current as u32, obj);
+        self.max.set_value(Some(max as u32), obj);
+    }
 
-            let max_value_digit = max % 10;
-            max /= 10;
-            let max_value_sprite = &mut self.sprites[(self.digits - i) * 2 - 1];
-            max_value_sprite.set_sprite(obj.sprite(SMALL_SPRITES.number(max_value_digit as u32)));
-        }
+    pub fn update(&mut self, obj: &'a ObjectController) {
+        self.current.update(obj);
     }
 }
 
+/// Which end of a number stays put as it gets more digits
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Alignment {
+    Left,
+    Right,
+}
+
 pub struct NumberDisplay<'a> {
-    objects: Vec<Object<'a>>,
+    /// Starting from the last digit, along with the digit each is showing
+    objects: Vec<(Object<'a>, u32)>,
     value: Option<u32>,
+    shown: Option<u32>,
+    frame: u32,
+
     position: Vector2D<i32>,
+    colour: NumberColour,
+    alignment: Alignment,
+    /// Numbers with fewer digits than this get leading zeros
+    padding: usize,
 }
 
 impl<'a> NumberDisplay<'a> {
     pub fn new(position: Vector2D<i32>) -> Self {
+        Self::new_coloured(position, NumberColour::Normal)
+    }
+
+    pub fn new_coloured(position: Vector2D<i32>, colour: NumberColour) -> Self {
         Self {
             objects: Vec::new(),
             value: None,
+            shown: None,
+            frame: 0,
             position,
+            colour,
+            alignment: Alignment::Right,
+            padding: 1,
         }
     }
 
+    pub fn aligned(mut self, alignment: Alignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+
+    pub fn padded(mut self, digits: usize) -> Self {
+        self.padding = digits.max(1);
+        self
+    }
+
     pub fn set_value(&mut self, new_value: Option<u32>, obj: &'a ObjectController) {
-        if self.value == new_value {
-            return;
+        self.value = new_value;
+
+        if self.shown != new_value {
+            self.shown = new_value;
+            self.redraw(obj);
         }
+    }
 
-        self.value = new_value;
+    /// Ticks towards `new_value` over the next few calls to `update` rather than jumping to it.
+    /// If nothing is shown yet, there is nothing to count from so it shows up straight away.
+    pub fn count_to(&mut self, new_value: u32, obj: &'a ObjectController) {
+        if self.shown.is_none() {
+            self.set_value(Some(new_value), obj);
+        } else {
+            self.value = Some(new_value);
+        }
+    }
 
-        self.objects.clear();
+    pub fn update(&mut self, obj: &'a ObjectController) {
+        if let (Some(shown), Some(value)) = (self.shown, self.value) {
+            if shown == value {
+                return;
+            }
 
-        if let Some(mut new_value) = new_value {
-            if new_value == 0 {
-                let mut zero_object = obj.object(obj.sprite(SMALL_SPRITES.number(0)));
-                zero_object.show().set_position(self.position);
+            self.frame = self.frame.wrapping_add(1);
+            if self.frame % COUNT_FRAMES_PER_STEP == 0 {
+                self.shown = Some(count_towards(shown, value));
+                self.redraw(obj);
+            }
+        }
+    }
 
-                self.objects.push(zero_object);
+    fn redraw(&mut self, obj: &'a ObjectController) {
+        let mut remaining = match self.shown {
+            Some(shown) => shown,
+            None => {
+                self.objects.clear();
                 return;
             }
+        };
+
+        let mut digit_count = 1;
+        let mut rest = remaining / 10;
+        while rest != 0 {
+            digit_count += 1;
+            rest /= 10;
+        }
+        let digit_count = digit_count.max(self.padding);
+
+        self.objects.truncate(digit_count);
+
+        for i in 0..digit_count {
+            let digit = remaining % 10;
+            remaining /= 10;
+
+            let position = self.digit_position(i, digit_count);
+
+            if let Some((digit_obj, shown)) = self.objects.get_mut(i) {
+                if *shown != digit {
+                    digit_obj
+                        .set_sprite(obj.sprite(SMALL_SPRITES.coloured_number(digit, self.colour)));
+                    *shown = digit;
+                }
+
+                digit_obj.set_position(position).show();
+            } else {
+                let mut digit_obj =
+                    obj.object(obj.sprite(SMALL_SPRITES.coloured_number(digit, self.colour)));
+                digit_obj.set_position(position).show();
+
+                self.objects.push((digit_obj, digit));
+            }
+        }
+    }
+
+    /// Where the `i`th digit from the end goes when there are `count` of them
+    fn digit_position(&self, i: usize, count: usize) -> Vector2D<i32> {
+        match self.alignment {
+            Alignment::Right => self.position - (i as i32 * 4, 0).into(),
+            Alignment::Left => self.position + ((count - 1 - i) as i32 * 4, 0).into(),
+        }
+    }
+
+    /// The position of the last digit for right aligned numbers, or the first digit for left
+    /// aligned ones
+    pub fn set_position(&mut self, position: Vector2D<i32>) {
+        self.position = position;
+
+        let count = self.objects.len();
+        for i in 0..count {
+            let digit_position = self.digit_position(i, count);
+            self.objects[i].0.set_position(digit_position);
+        }
+    }
+
+    pub fn show(&mut self) {
+        for (obj, _) in self.objects.iter_mut() {
+            obj.show();
+        }
+    }
+
+    pub fn hide(&mut self) {
+        for (obj, _) in self.objects.iter_mut() {
+            obj.hide();
+        }
+    }
+}
+
+pub struct TextDisplay<'a> {
+    objects: Vec<(i32, Object<'a>)>,
+    position: Vector2D<i32>,
+}
+
+impl<'a> TextDisplay<'a> {
+    pub fn new(position: Vector2D<i32>) -> Self {
+        Self {
+            objects: Vec::new(),
+            position,
+        }
+    }
 
-            let mut digit = 0;
-            while new_value != 0 {
-                let current_value_digit = new_value % 10;
-                new_value /= 10;
+    pub fn set_text(&mut self, text: &str, obj: &'a ObjectController) {
+        self.objects.clear();
 
-                let mut current_value_obj =
-                    obj.object(obj.sprite(SMALL_SPRITES.number(current_value_digit)));
+        for (i, c) in text.chars().enumerate() {
+            if let Some(sprite) = SMALL_SPRITES.character(c) {
+                let offset = i as i32 * 4;
 
-                current_value_obj
+                let mut character_obj = obj.object(obj.sprite(sprite));
+                character_obj
                     .show()
-                    .set_position(self.position - (digit * 4, 0).into());
+                    .set_position(self.position + (offset, 0).into());
+
+                self.objects.push((offset, character_obj));
+            }
+        }
+    }
+
+    pub fn set_position(&mut self, position: Vector2D<i32>) {
+        self.position = position;
 
-                digit += 1;
+        for (offset, character_obj) in self.objects.iter_mut() {
+            character_obj.set_position(position + (*offset, 0).into());
+        }
+    }
+
+    pub fn show(&mut self) {
+        for (_, obj) in self.objects.iter_mut() {
+            obj.show();
+        }
+    }
+
+    pub fn hide(&mut self) {
+        for (_, obj) in self.objects.iter_mut() {
+            obj.hide();
+        }
+    }
+}
 
-                self.objects.push(current_value_obj);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BAR_COLOURS: [(BarColour, &str); 3] = [
+        (BarColour::Health, "health bar"),
+        (BarColour::Cooldown, "cooldown bar"),
+        (BarColour::Shield, "shield bar"),
+    ];
+
+    #[test_case]
+    fn every_bar_cell_has_its_own_sprite_in_order(_gba: &mut agb::Gba) {
+        for (colour, tag) in BAR_COLOURS {
+            let tag = SPRITES.tags().get(tag);
+            let mut index = 0;
+
+            for width in 1..=8 {
+                for filled in 0..=width {
+                    for ghost in 0..=width - filled {
+                        assert!(
+                            core::ptr::eq(
+                                SMALL_SPRITES.bar(colour, width, filled, ghost),
+                                tag.sprite(index)
+                            ),
+                            "{:?} cell {} wide with {} filled and {} ghost isn't sprite {}",
+                            colour,
+                            width,
+                            filled,
+                            ghost,
+                            index
+                        );
+
+                        index += 1;
+                    }
+                }
             }
+
+            assert_eq!(index, tag.sprites().len());
+        }
+    }
+
+    #[test_case]
+    fn bar_cells_add_up_to_the_whole_bar(_gba: &mut agb::Gba) {
+        for max in [1, 7, 8, 9, 24, 30] {
+            let cells = (max + 7) / 8;
+
+            assert_eq!((0..cells).map(|i| cell_width(i, max)).sum::<usize>(), max);
+            assert!((0..cells).all(|i| (1..=8).contains(&cell_width(i, max))));
         }
     }
 }
@@ -0,0 +1,303 @@
+use agb::display::object::ObjectController;
+use agb::fixnum::Vector2D;
+use agb::input::{Button, Tri};
+use alloc::vec::Vec;
+
+use crate::graphics::{NumberDisplay, TextDisplay, SMALL_SPRITES};
+use crate::sfx::SoundEvent;
+use crate::{save, Agb};
+
+pub const LEADERBOARD_SIZE: usize = 10;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LeaderboardEntry {
+    pub level: u32,
+    /// How many frames the run took, used to break ties between runs reaching the same level
+    pub time: u32,
+    pub initials: [u8; 3],
+}
+
+impl LeaderboardEntry {
+    pub const EMPTY: Self = Self {
+        level: 0,
+        time: 0,
+        initials: [b'-'; 3],
+    };
+
+    fn is_empty(&self) -> bool {
+        self.level == 0
+    }
+
+    fn is_better_than(&self, other: &Self) -> bool {
+        other.is_empty()
+            || self.level > other.level
+            || (self.level == other.level && self.time < other.time)
+    }
+}
+
+fn position_for(
+    leaderboard: &[LeaderboardEntry; LEADERBOARD_SIZE],
+    entry: &LeaderboardEntry,
+) -> Option<usize> {
+    leaderboard
+        .iter()
+        .position(|existing| entry.is_better_than(existing))
+}
+
+pub fn qualifies(level: u32, time: u32) -> bool {
+    position_for(
+        &save::load_leaderboard(),
+        &LeaderboardEntry {
+            level,
+            time,
+            initials: LeaderboardEntry::EMPTY.initials,
+        },
+    )
+    .is_some()
+}
+
+/// Puts the entry in its place, moving the ones below it down and the last one off the end.
+/// Returns whether it made it on at all.
+fn insert_into(
+    leaderboard: &mut [LeaderboardEntry; LEADERBOARD_SIZE],
+    entry: LeaderboardEntry,
+) -> bool {
+    if let Some(position) = position_for(leaderboard, &entry) {
+        leaderboard.copy_within(position..LEADERBOARD_SIZE - 1, position + 1);
+        leaderboard[position] = entry;
+        true
+    } else {
+        false
+    }
+}
+
+pub fn insert(entry: LeaderboardEntry) {
+    let mut leaderboard = save::load_leaderboard();
+
+    if insert_into(&mut leaderboard, entry) {
+        save::save_leaderboard(&leaderboard);
+    }
+}
+
+fn as_text(letters: &[u8]) -> &str {
+    core::str::from_utf8(letters).unwrap_or("-")
+}
+
+/// The run's time as `mm:ss`, which stops at 99:59
+fn time_text(frames: u32) -> [u8; 5] {
+    let seconds = (frames / 60).min(99 * 60 + 59);
+    let (minutes, seconds) = (seconds / 60, seconds % 60);
+
+    [
+        b'0' + (minutes / 10) as u8,
+        b'0' + (minutes % 10) as u8,
+        b':',
+        b'0' + (seconds / 10) as u8,
+        b'0' + (seconds % 10) as u8,
+    ]
+}
+
+/// Shows the initials, level reached and time taken for each filled entry, one per row
+pub struct LeaderboardDisplay<'a> {
+    _rows: Vec<(TextDisplay<'a>, NumberDisplay<'a>, TextDisplay<'a>)>,
+}
+
+impl<'a> LeaderboardDisplay<'a> {
+    pub fn new(position: Vector2D<i32>, obj: &'a ObjectController) -> Self {
+        let rows = save::load_leaderboard()
+            .iter()
+            .take_while(|entry| !entry.is_empty())
+            .enumerate()
+            .map(|(i, entry)| {
+                let row_position = position + (0, i as i32 * 7).into();
+
+                let mut initials = TextDisplay::new(row_position);
+                initials.set_text(as_text(&entry.initials), obj);
+
+                let mut level = NumberDisplay::new(row_position + (28, 0).into());
+                level.set_value(Some(entry.level), obj);
+
+                let mut time = TextDisplay::new(row_position + (34, 0).into());
+                time.set_text(as_text(&time_text(entry.time)), obj);
+
+                (initials, level, time)
+            })
+            .collect();
+
+        Self { _rows: rows }
+    }
+}
+
+const INITIALS_X: i32 = 112;
+const INITIALS_Y: i32 = 76;
+
+fn next_letter(letter: u8, direction: Tri) -> u8 {
+    let index = (letter - b'A') as isize + direction as isize;
+    b'A' + index.rem_euclid(26) as u8
+}
+
+/// Lets the player pick three letters to put next to their run on the leaderboard. Returns
+/// `None` if they'd rather skip it and leave the run off.
+pub(crate) fn enter_initials(agb: &mut Agb, level: u32) -> Option<[u8; 3]> {
+    let obj = &agb.obj;
+
+    let mut title = TextDisplay::new((INITIALS_X - 14, 40).into());
+    title.set_text("new record", obj);
+
+    let mut level_text = TextDisplay::new((INITIALS_X - 10, 52).into());
+    level_text.set_text("level", obj);
+    let mut level_display = NumberDisplay::new((INITIALS_X + 20, 52).into());
+    level_display.set_value(Some(level), obj);
+
+    let mut skip_text = TextDisplay::new((INITIALS_X - 14, INITIALS_Y + 16).into());
+    skip_text.set_text("select: skip", obj);
+
+    let mut initials = [b'A'; 3];
+    let mut letters: Vec<TextDisplay> = (0..initials.len())
+        .map(|i| {
+            let mut letter = TextDisplay::new((INITIALS_X + i as i32 * 8, INITIALS_Y).into());
+            letter.set_text(as_text(&initials[i..i + 1]), obj);
+            letter
+        })
+        .collect();
+
+    let mut cursor_obj = obj.object(obj.sprite(SMALL_SPRITES.character('_').unwrap()));
+    cursor_obj.show();
+
+    let mut input = agb::input::ButtonController::new();
+    let mut counter = 0usize;
+    let mut selected = 0;
+
+    loop {
+        counter = counter.wrapping_add(1);
+        input.update();
+
+        let ud: Tri = (
+            input.is_just_pressed(Button::UP),
+            input.is_just_pressed(Button::DOWN),
+        )
+            .into();
+        let lr: Tri = (
+            input.is_just_pressed(Button::LEFT),
+            input.is_just_pressed(Button::RIGHT),
+        )
+            .into();
+
+        if ud != Tri::Zero {
+            initials[selected] = next_letter(initials[selected], ud);
+            letters[selected].set_text(as_text(&initials[selected..selected + 1]), obj);
+            agb.sfx.play(SoundEvent::MoveCursor);
+        }
+
+        if lr != Tri::Zero {
+            selected = (selected as isize + lr as isize).clamp(0, 2) as usize;
+            agb.sfx.play(SoundEvent::MoveCursor);
+        }
+
+        if input.is_just_pressed(Button::SELECT) {
+            agb.sfx.play(SoundEvent::Back);
+            return None;
+        }
+
+        if input.is_just_pressed(Button::START)
+            || (input.is_just_pressed(Button::A) && selected == initials.len() - 1)
+        {
+            agb.sfx.play(SoundEvent::Accept);
+            break;
+        } else if input.is_just_pressed(Button::A) {
+            selected += 1;
+            agb.sfx.play(SoundEvent::Select);
+        } else if input.is_just_pressed(Button::B) && selected > 0 {
+            selected -= 1;
+            agb.sfx.play(SoundEvent::Back);
+        }
+
+        cursor_obj.set_position((INITIALS_X + selected as i32 * 8, INITIALS_Y + 2).into());
+        if (counter / 16) % 2 == 0 {
+            cursor_obj.show();
+        } else {
+            cursor_obj.hide();
+        }
+
+        agb.star_background.update();
+        agb.sfx.frame();
+        agb.vblank.wait_for_vblank();
+        agb.obj.commit();
+        agb.star_background.commit(&mut agb.vram);
+    }
+
+    Some(initials)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(level: u32, time: u32) -> LeaderboardEntry {
+        LeaderboardEntry {
+            level,
+            time,
+            initials: *b"ABC",
+        }
+    }
+
+    #[test_case]
+    fn higher_level_is_better_whatever_the_time(_gba: &mut agb::Gba) {
+        assert!(entry(5, 10_000).is_better_than(&entry(4, 100)));
+        assert!(!entry(4, 100).is_better_than(&entry(5, 10_000)));
+    }
+
+    #[test_case]
+    fn faster_time_breaks_ties(_gba: &mut agb::Gba) {
+        assert!(entry(5, 100).is_better_than(&entry(5, 200)));
+        assert!(!entry(5, 200).is_better_than(&entry(5, 100)));
+        assert!(!entry(5, 100).is_better_than(&entry(5, 100)));
+    }
+
+    #[test_case]
+    fn anything_beats_an_empty_entry(_gba: &mut agb::Gba) {
+        assert!(entry(1, u32::MAX).is_better_than(&LeaderboardEntry::EMPTY));
+    }
+
+    #[test_case]
+    fn insert_keeps_the_leaderboard_in_order(_gba: &mut agb::Gba) {
+        let mut leaderboard = [LeaderboardEntry::EMPTY; LEADERBOARD_SIZE];
+
+        assert!(insert_into(&mut leaderboard, entry(3, 100)));
+        assert!(insert_into(&mut leaderboard, entry(5, 100)));
+        assert!(insert_into(&mut leaderboard, entry(3, 50)));
+
+        assert_eq!(
+            leaderboard[..4],
+            [
+                entry(5, 100),
+                entry(3, 50),
+                entry(3, 100),
+                LeaderboardEntry::EMPTY
+            ]
+        );
+    }
+
+    #[test_case]
+    fn insert_pushes_the_last_entry_off_a_full_leaderboard(_gba: &mut agb::Gba) {
+        let mut leaderboard = [entry(10, 100); LEADERBOARD_SIZE];
+        leaderboard[LEADERBOARD_SIZE - 1] = entry(2, 100);
+
+        assert!(insert_into(&mut leaderboard, entry(10, 50)));
+
+        assert_eq!(leaderboard[0], entry(10, 50));
+        assert!(leaderboard[1..]
+            .iter()
+            .all(|&existing| existing == entry(10, 100)));
+    }
+
+    #[test_case]
+    fn insert_leaves_a_full_leaderboard_alone_if_the_entry_is_worse(_gba: &mut agb::Gba) {
+        let mut leaderboard = [entry(10, 100); LEADERBOARD_SIZE];
+
+        assert!(!insert_into(&mut leaderboard, entry(10, 100)));
+        assert!(!insert_into(&mut leaderboard, entry(9, 1)));
+
+        assert_eq!(leaderboard, [entry(10, 100); LEADERBOARD_SIZE]);
+    }
+}
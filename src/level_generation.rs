@@ -1,7 +1,7 @@
 use agb::{hash_map::HashMap, rng};
 use alloc::vec::Vec;
 
-use crate::{battle::EnemyAttack, Face};
+use crate::{battle::EnemyAttack, progress::unlocks::FaceUnlocks, Face};
 
 pub struct GeneratedAttack {
     pub attack: EnemyAttack,
@@ -37,7 +37,7 @@ fn generate_cooldown(current_level: u32) -> u32 {
     rng::gen().rem_euclid((5 * 60 - current_level as i32 * 10).max(1)) as u32 + 2 * 60
 }
 
-pub fn generate_upgrades(level: u32) -> Vec<Face> {
+pub fn generate_upgrades(level: u32, face_unlocks: &FaceUnlocks) -> Vec<Face> {
     let mut upgrade_values = HashMap::new();
 
     upgrade_values.insert(Face::Shoot, 5);
@@ -57,7 +57,11 @@ pub fn generate_upgrades(level: u32) -> Vec<Face> {
     upgrade_values.insert(Face::BurstShield, 30);
     upgrade_values.insert(Face::Invert, 30);
 
-    let potential_upgrades: Vec<Face> = upgrade_values.keys().cloned().collect();
+    let potential_upgrades: Vec<Face> = upgrade_values
+        .keys()
+        .cloned()
+        .filter(|&face| face_unlocks.is_unlocked(face))
+        .collect();
 
     let mut upgrades = Vec::new();
 
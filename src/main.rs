@@ -9,6 +9,10 @@
 // using the #[agb::entry] proc macro. Failing to do so will cause failure in linking
 // which won't be a particularly clear error message.
 #![no_main]
+// This is required to allow writing tests
+#![cfg_attr(test, feature(custom_test_frameworks))]
+#![cfg_attr(test, reexport_test_harness_main = "test_main")]
+#![cfg_attr(test, test_runner(agb::test_runner::test_runner))]
 
 use agb::display;
 use agb::display::object::ObjectController;
@@ -24,13 +28,22 @@ mod background;
 mod battle;
 mod customise;
 mod graphics;
+mod leaderboard;
 mod level_generation;
+mod menu;
+mod progress;
 mod save;
+mod settings;
 mod sfx;
+mod summary;
+mod ui;
 
 use background::{show_title_screen, StarBackground};
 use battle::BattleResult;
 use graphics::NumberDisplay;
+use leaderboard::{LeaderboardDisplay, LeaderboardEntry};
+use progress::{GameEvent, Progress};
+use settings::Settings;
 use sfx::Sfx;
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
@@ -54,6 +67,28 @@ pub enum Face {
     Invert,
 }
 
+pub const NUMBER_OF_FACES: usize = Face::Invert as usize + 1;
+
+pub const ALL_FACES: [Face; NUMBER_OF_FACES] = [
+    Face::Shoot,
+    Face::Shield,
+    Face::Malfunction,
+    Face::Heal,
+    Face::Bypass,
+    Face::DoubleShot,
+    Face::TripleShot,
+    Face::Blank,
+    Face::Disrupt,
+    Face::MalfunctionShot,
+    Face::DoubleShield,
+    Face::TripleShield,
+    Face::DoubleShieldValue,
+    Face::DoubleShotValue,
+    Face::TripleShotValue,
+    Face::BurstShield,
+    Face::Invert,
+];
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
 pub enum Ship {
     Player,
@@ -93,6 +128,10 @@ struct Agb<'a> {
     star_background: StarBackground<'a>,
     vram: VRamManager,
     sfx: Sfx<'a>,
+    progress: Progress,
+    settings: Settings,
+    /// How many frames the current run has been going for
+    run_frames: u32,
 }
 
 fn main(mut gba: agb::Gba) -> ! {
@@ -142,7 +181,10 @@ fn main(mut gba: agb::Gba) -> ! {
     mixer.enable();
     let _interrupt_handler = mixer.setup_interrupt_handler();
 
-    let sfx = Sfx::new(&mut mixer);
+    let settings = save::load_settings();
+
+    let mut sfx = Sfx::new(&mut mixer);
+    sfx.apply_settings(&settings);
 
     let mut agb = Agb {
         obj: gfx,
@@ -150,6 +192,9 @@ fn main(mut gba: agb::Gba) -> ! {
         star_background,
         vram,
         sfx,
+        progress: Progress::load(),
+        settings,
+        run_frames: 0,
     };
 
     loop {
@@ -161,23 +206,39 @@ fn main(mut gba: agb::Gba) -> ! {
 
         agb.sfx.title_screen();
 
-        {
-            show_title_screen(&mut help_background, &mut agb.vram, &mut agb.sfx);
-            let mut score_display = NumberDisplay::new((216, 9).into());
-            score_display.set_value(Some(save::load_high_score()), &agb.obj);
-            agb.obj.commit();
-            agb.star_background.hide();
-
-            let mut input = agb::input::ButtonController::new();
-            loop {
-                let _ = agb::rng::gen();
-                input.update();
-                if input.is_just_pressed(agb::input::Button::all()) {
-                    break;
+        loop {
+            let show_extras = {
+                show_title_screen(&mut help_background, &mut agb.vram, &mut agb.sfx);
+                let mut score_display = NumberDisplay::new((216, 9).into());
+                score_display.set_value(Some(0), &agb.obj);
+                score_display.count_to(save::load_high_score(), &agb.obj);
+                let _leaderboard_display = LeaderboardDisplay::new((184, 44).into(), &agb.obj);
+                agb.obj.commit();
+                agb.star_background.hide();
+
+                let mut input = agb::input::ButtonController::new();
+                loop {
+                    let _ = agb::rng::gen();
+                    input.update();
+                    if input.is_just_pressed(agb::input::Button::SELECT) {
+                        break true;
+                    }
+                    if input.is_just_pressed(agb::input::Button::all()) {
+                        break false;
+                    }
+                    score_display.update(&agb.obj);
+                    agb.vblank.wait_for_vblank();
+                    agb.obj.commit();
+                    agb.sfx.frame();
                 }
-                agb.vblank.wait_for_vblank();
-                agb.sfx.frame();
+            };
+
+            if !show_extras {
+                break;
             }
+
+            help_background.hide();
+            menu::extras_menu(&mut agb);
         }
 
         agb.obj.commit();
@@ -190,6 +251,9 @@ fn main(mut gba: agb::Gba) -> ! {
         background::load_palettes(&mut agb.vram);
         agb.star_background.show();
 
+        agb.run_frames = 0;
+        agb.progress.start_run();
+
         loop {
             dice = customise::customise_screen(
                 &mut agb,
@@ -206,14 +270,31 @@ fn main(mut gba: agb::Gba) -> ! {
                 BattleResult::Loss => {
                     agb.obj.commit();
                     agb.sfx.customise();
-                    if save::load_high_score() < current_level {
+
+                    let new_best = save::load_high_score() < current_level;
+                    if new_best {
                         save::save_high_score(current_level);
                     }
+
+                    summary::run_summary(&mut agb, &dice, current_level, new_best);
+                    agb.obj.commit();
+
+                    if leaderboard::qualifies(current_level, agb.run_frames) {
+                        if let Some(initials) = leaderboard::enter_initials(&mut agb, current_level)
+                        {
+                            leaderboard::insert(LeaderboardEntry {
+                                level: current_level,
+                                time: agb.run_frames,
+                                initials,
+                            });
+                        }
+                    }
                     break;
                 }
             }
 
             current_level += 1;
+            agb.progress.report(GameEvent::LevelReached(current_level));
 
             if current_level % 5 == 0 && dice.dice.len() < 5 {
                 dice.dice.push(basic_die.clone());
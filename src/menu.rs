@@ -0,0 +1,99 @@
+use alloc::vec::Vec;
+
+use crate::graphics::TextDisplay;
+use crate::progress::{achievements, statistics};
+use crate::ui::{CursorStyle, Focus, FocusEvent, FocusSounds, Navigation};
+use crate::{settings, Agb};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ExtrasItem {
+    Achievements,
+    Statistics,
+    Settings,
+}
+
+const EXTRAS_ITEMS: [ExtrasItem; 3] = [
+    ExtrasItem::Achievements,
+    ExtrasItem::Statistics,
+    ExtrasItem::Settings,
+];
+
+impl ExtrasItem {
+    fn name(self) -> &'static str {
+        match self {
+            ExtrasItem::Achievements => "achievements",
+            ExtrasItem::Statistics => "statistics",
+            ExtrasItem::Settings => "settings",
+        }
+    }
+}
+
+const MENU_X: i32 = 96;
+const MENU_Y: i32 = 60;
+
+/// The menu reached by pressing select on the title screen
+pub(crate) fn extras_menu(agb: &mut Agb) {
+    crate::background::load_palettes(&mut agb.vram);
+    agb.star_background.show();
+
+    let mut cursor = 0;
+
+    loop {
+        let selected = {
+            let obj = &agb.obj;
+
+            let _items: Vec<_> = EXTRAS_ITEMS
+                .iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    let mut text = TextDisplay::new((MENU_X, MENU_Y + i as i32 * 12).into());
+                    text.set_text(item.name(), obj);
+                    text
+                })
+                .collect();
+
+            let mut focus = Focus::new(
+                Navigation::Column,
+                (0..EXTRAS_ITEMS.len())
+                    .map(|i| (MENU_X - 8, MENU_Y + i as i32 * 12).into())
+                    .collect(),
+                CursorStyle::Pointer,
+                FocusSounds::MENU,
+                obj,
+            );
+            focus.set_index(cursor);
+
+            let mut input = agb::input::ButtonController::new();
+
+            loop {
+                input.update();
+
+                match focus.update(&input, &mut agb.sfx, obj) {
+                    Some(FocusEvent::Selected(item)) => {
+                        cursor = item;
+                        break Some(EXTRAS_ITEMS[item]);
+                    }
+                    Some(FocusEvent::Back) => break None,
+                    _ => {}
+                }
+
+                agb.star_background.update();
+                agb.sfx.frame();
+                agb.vblank.wait_for_vblank();
+                agb.obj.commit();
+                agb.star_background.commit(&mut agb.vram);
+            }
+        };
+
+        agb.obj.commit();
+
+        match selected {
+            Some(ExtrasItem::Achievements) => achievements::achievements_screen(agb),
+            Some(ExtrasItem::Statistics) => statistics::statistics_screen(agb),
+            Some(ExtrasItem::Settings) => settings::settings_screen(agb),
+            None => break,
+        }
+    }
+
+    agb.star_background.hide();
+}
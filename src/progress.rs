@@ -0,0 +1,61 @@
+use alloc::vec::Vec;
+
+use crate::{battle::Action, Face};
+
+use self::achievements::Achievements;
+use self::statistics::{RunStatistics, Statistics};
+use self::unlocks::FaceUnlocks;
+
+pub mod achievements;
+pub mod statistics;
+pub mod unlocks;
+
+/// Something which happened during a run. The screens report these without caring who is
+/// listening, and everything which tracks the player's progress gets to look at them.
+#[derive(Debug, Clone)]
+pub enum GameEvent {
+    ActionApplied(Action),
+    DiceAccepted {
+        faces: Vec<Face>,
+        actions: Vec<Action>,
+    },
+    BattleWon {
+        damage_taken: u32,
+    },
+    DiceRolled {
+        count: u32,
+    },
+    DamageDealt(u32),
+    DamageBlocked(u32),
+    BattleLost,
+    LevelReached(u32),
+}
+
+pub struct Progress {
+    pub achievements: Achievements,
+    pub face_unlocks: FaceUnlocks,
+    pub statistics: Statistics,
+    pub run: RunStatistics,
+}
+
+impl Progress {
+    pub fn load() -> Self {
+        Self {
+            achievements: Achievements::load(),
+            face_unlocks: FaceUnlocks::load(),
+            statistics: Statistics::load(),
+            run: RunStatistics::ZERO,
+        }
+    }
+
+    pub fn start_run(&mut self) {
+        self.run = RunStatistics::ZERO;
+    }
+
+    pub fn report(&mut self, event: GameEvent) {
+        self.statistics.report(&event);
+        self.run.report(&event);
+        self.achievements.report(&event);
+        self.face_unlocks.report(&event, &self.statistics);
+    }
+}
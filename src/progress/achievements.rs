@@ -0,0 +1,288 @@
+use agb::display::object::{Object, ObjectController};
+use agb::fixnum::Vector2D;
+use alloc::collections::VecDeque;
+
+use crate::battle::Action;
+use crate::graphics::{TextDisplay, FACE_SPRITES};
+use crate::ui::{CursorStyle, Focus, FocusEvent, FocusSounds, Icons, Navigation};
+use crate::{save, Agb, Face};
+
+use super::GameEvent;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Achievement {
+    WarpSpeed,
+    DeepSpace,
+    Untouchable,
+    Fortress,
+    Overkill,
+    BigBang,
+    TopsyTurvy,
+    Jammed,
+}
+
+pub const ALL_ACHIEVEMENTS: [Achievement; 8] = [
+    Achievement::WarpSpeed,
+    Achievement::DeepSpace,
+    Achievement::Untouchable,
+    Achievement::Fortress,
+    Achievement::Overkill,
+    Achievement::BigBang,
+    Achievement::TopsyTurvy,
+    Achievement::Jammed,
+];
+
+impl Achievement {
+    pub fn name(self) -> &'static str {
+        match self {
+            Achievement::WarpSpeed => "warp speed",
+            Achievement::DeepSpace => "deep space",
+            Achievement::Untouchable => "untouchable",
+            Achievement::Fortress => "fortress",
+            Achievement::Overkill => "overkill",
+            Achievement::BigBang => "big bang",
+            Achievement::TopsyTurvy => "topsy turvy",
+            Achievement::Jammed => "jammed",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Achievement::WarpSpeed => "reach level 10",
+            Achievement::DeepSpace => "reach level 20",
+            Achievement::Untouchable => "win without damage",
+            Achievement::Fortress => "raise 5 shields",
+            Achievement::Overkill => "shoot for 20+",
+            Achievement::BigBang => "burst for 10+",
+            Achievement::TopsyTurvy => "invert for 10+",
+            Achievement::Jammed => "disrupt for 6+",
+        }
+    }
+
+    pub fn icon(self) -> Face {
+        match self {
+            Achievement::WarpSpeed => Face::Bypass,
+            Achievement::DeepSpace => Face::TripleShotValue,
+            Achievement::Untouchable => Face::Heal,
+            Achievement::Fortress => Face::TripleShield,
+            Achievement::Overkill => Face::TripleShot,
+            Achievement::BigBang => Face::BurstShield,
+            Achievement::TopsyTurvy => Face::Invert,
+            Achievement::Jammed => Face::Disrupt,
+        }
+    }
+
+    fn is_unlocked_by(self, event: &GameEvent) -> bool {
+        match (self, event) {
+            (Achievement::WarpSpeed, GameEvent::LevelReached(level)) => *level >= 10,
+            (Achievement::DeepSpace, GameEvent::LevelReached(level)) => *level >= 20,
+            (Achievement::Untouchable, GameEvent::BattleWon { damage_taken }) => *damage_taken == 0,
+            (
+                Achievement::Fortress,
+                GameEvent::ActionApplied(Action::PlayerActivateShield { amount }),
+            ) => *amount >= 5,
+            (
+                Achievement::Overkill,
+                GameEvent::ActionApplied(Action::PlayerShoot { damage, .. }),
+            ) => *damage >= 20,
+            (
+                Achievement::BigBang,
+                GameEvent::ActionApplied(Action::PlayerSendBurstShield { damage }),
+            ) => *damage >= 10,
+            (Achievement::TopsyTurvy, GameEvent::DiceAccepted { faces, actions }) => {
+                let inverted = faces.iter().filter(|&&face| face == Face::Invert).count() % 2 == 1;
+
+                inverted
+                    && actions.iter().any(
+                        |action| matches!(action, Action::PlayerShoot { damage, .. } if *damage >= 10),
+                    )
+            }
+            (Achievement::Jammed, GameEvent::ActionApplied(Action::PlayerDisrupt { amount })) => {
+                *amount >= 6
+            }
+            _ => false,
+        }
+    }
+}
+
+pub struct Achievements {
+    unlocked: u32,
+    to_announce: VecDeque<Achievement>,
+}
+
+impl Achievements {
+    pub fn load() -> Self {
+        Self {
+            unlocked: save::load_achievements(),
+            to_announce: VecDeque::new(),
+        }
+    }
+
+    pub fn is_unlocked(&self, achievement: Achievement) -> bool {
+        self.unlocked & (1 << achievement as u32) != 0
+    }
+
+    pub fn report(&mut self, event: &GameEvent) {
+        for achievement in ALL_ACHIEVEMENTS {
+            if !self.is_unlocked(achievement) && achievement.is_unlocked_by(event) {
+                self.unlocked |= 1 << achievement as u32;
+                self.to_announce.push_back(achievement);
+
+                save::save_achievements(self.unlocked);
+            }
+        }
+    }
+
+    fn next_to_announce(&mut self) -> Option<Achievement> {
+        self.to_announce.pop_front()
+    }
+}
+
+const TOAST_SLIDE_FRAMES: u32 = 16;
+const TOAST_HOLD_FRAMES: u32 = 2 * 60;
+
+/// Slides newly unlocked achievements in from the top of the screen, one at a time
+pub struct AchievementToast<'a> {
+    position: Vector2D<i32>,
+    frame: u32,
+    icon: Option<Object<'a>>,
+    name: TextDisplay<'a>,
+}
+
+impl<'a> AchievementToast<'a> {
+    pub fn new(position: Vector2D<i32>) -> Self {
+        Self {
+            position,
+            frame: 0,
+            icon: None,
+            name: TextDisplay::new((0, 0).into()),
+        }
+    }
+
+    pub fn update(&mut self, achievements: &mut Achievements, obj: &'a ObjectController) {
+        if self.icon.is_none() {
+            if let Some(achievement) = achievements.next_to_announce() {
+                let mut icon =
+                    obj.object(obj.sprite(FACE_SPRITES.sprite_for_face(achievement.icon())));
+                icon.set_z(-1).show();
+
+                self.icon = Some(icon);
+                self.name.set_text(achievement.name(), obj);
+                self.frame = 0;
+            } else {
+                return;
+            }
+        }
+
+        let slide_distance = 32 + self.position.y;
+        let offset = if self.frame < TOAST_SLIDE_FRAMES {
+            slide_distance - (self.frame * slide_distance as u32 / TOAST_SLIDE_FRAMES) as i32
+        } else if self.frame < TOAST_SLIDE_FRAMES + TOAST_HOLD_FRAMES {
+            0
+        } else {
+            ((self.frame - TOAST_SLIDE_FRAMES - TOAST_HOLD_FRAMES) * slide_distance as u32
+                / TOAST_SLIDE_FRAMES) as i32
+        };
+
+        let position = self.position - (0, offset).into();
+
+        if let Some(icon) = &mut self.icon {
+            icon.set_position(position);
+        }
+        self.name.set_position(position + (36, 14).into());
+
+        self.frame += 1;
+
+        if self.frame > TOAST_SLIDE_FRAMES * 2 + TOAST_HOLD_FRAMES {
+            self.icon = None;
+            self.name.set_text("", obj);
+        }
+    }
+}
+
+const GRID_COLUMNS: usize = 4;
+
+fn grid_position(idx: usize) -> (i32, i32) {
+    (
+        (idx % GRID_COLUMNS) as i32 * 40 + 44,
+        (idx / GRID_COLUMNS) as i32 * 40 + 24,
+    )
+}
+
+pub(crate) fn achievements_screen(agb: &mut Agb) {
+    let obj = &agb.obj;
+
+    let mut icons = Icons::default();
+    icons.set(
+        ALL_ACHIEVEMENTS
+            .iter()
+            .enumerate()
+            .map(|(idx, &achievement)| {
+                let face = if agb.progress.achievements.is_unlocked(achievement) {
+                    achievement.icon()
+                } else {
+                    Face::Blank
+                };
+
+                let (x, y) = grid_position(idx);
+                (
+                    (x - 24 / 2, y - 24 / 2).into(),
+                    FACE_SPRITES.sprite_for_face(face),
+                )
+            }),
+        obj,
+    );
+
+    let mut focus = Focus::new(
+        Navigation::Grid {
+            columns: GRID_COLUMNS,
+        },
+        (0..ALL_ACHIEVEMENTS.len())
+            .map(|idx| {
+                let (x, y) = grid_position(idx);
+                (x - 32 / 2, y - 32 / 2).into()
+            })
+            .collect(),
+        CursorStyle::SelectBox,
+        FocusSounds {
+            selected: None,
+            ..FocusSounds::MENU
+        },
+        obj,
+    );
+
+    let mut name = TextDisplay::new((32, 112).into());
+    let mut description = TextDisplay::new((32, 122).into());
+
+    let mut input = agb::input::ButtonController::new();
+    let mut cursor_moved = true;
+
+    loop {
+        input.update();
+
+        match focus.update(&input, &mut agb.sfx, obj) {
+            Some(FocusEvent::Back) => break,
+            // A doesn't do anything here, but the cursor can still move on the frame it's pressed
+            Some(_) => cursor_moved = true,
+            None => {}
+        }
+
+        if cursor_moved {
+            let achievement = ALL_ACHIEVEMENTS[focus.index()];
+            if agb.progress.achievements.is_unlocked(achievement) {
+                name.set_text(achievement.name(), obj);
+            } else {
+                name.set_text("locked", obj);
+            }
+            description.set_text(achievement.description(), obj);
+
+            cursor_moved = false;
+        }
+
+        agb.star_background.update();
+        agb.sfx.frame();
+        agb.vblank.wait_for_vblank();
+        agb.obj.commit();
+        agb.star_background.commit(&mut agb.vram);
+    }
+}
@@ -0,0 +1,255 @@
+use agb::display::object::ObjectController;
+use alloc::vec::Vec;
+
+use crate::graphics::{NumberDisplay, TextDisplay, FACE_SPRITES};
+use crate::ui::{CursorStyle, Focus, FocusEvent, FocusSounds, Icons, Navigation};
+use crate::{save, Agb, Face, ALL_FACES, NUMBER_OF_FACES};
+
+use super::GameEvent;
+
+/// Counters which are kept across every run
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Counters {
+    pub battles_won: u32,
+    pub battles_lost: u32,
+    pub dice_rolled: u32,
+    pub accepts: u32,
+    pub damage_dealt: u32,
+    pub damage_blocked: u32,
+}
+
+impl Counters {
+    pub const ZERO: Self = Self {
+        battles_won: 0,
+        battles_lost: 0,
+        dice_rolled: 0,
+        accepts: 0,
+        damage_dealt: 0,
+        damage_blocked: 0,
+    };
+}
+
+pub struct Statistics {
+    counters: Counters,
+    face_uses: [u32; NUMBER_OF_FACES],
+}
+
+impl Statistics {
+    pub fn load() -> Self {
+        Self {
+            counters: save::load_statistics(),
+            face_uses: save::load_face_uses(),
+        }
+    }
+
+    pub fn counters(&self) -> &Counters {
+        &self.counters
+    }
+
+    /// How many times this face has been accepted
+    pub fn face_uses(&self, face: Face) -> u32 {
+        self.face_uses[face as usize]
+    }
+
+    pub fn report(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::DiceAccepted { faces, .. } => {
+                self.counters.accepts += 1;
+
+                for &face in faces {
+                    self.face_uses[face as usize] += 1;
+                }
+
+                save::save_face_uses(&self.face_uses);
+            }
+            GameEvent::DiceRolled { count } => self.counters.dice_rolled += count,
+            GameEvent::DamageDealt(amount) => self.counters.damage_dealt += amount,
+            GameEvent::DamageBlocked(amount) => self.counters.damage_blocked += amount,
+            GameEvent::BattleWon { .. } => self.counters.battles_won += 1,
+            GameEvent::BattleLost => self.counters.battles_lost += 1,
+            _ => return,
+        }
+
+        save::save_statistics(&self.counters);
+    }
+}
+
+/// What happened in just the current run, for the summary at the end of it
+#[derive(Clone, Debug)]
+pub struct RunStatistics {
+    pub battles_won: u32,
+    pub damage_dealt: u32,
+    face_uses: [u32; NUMBER_OF_FACES],
+}
+
+impl RunStatistics {
+    pub const ZERO: Self = Self {
+        battles_won: 0,
+        damage_dealt: 0,
+        face_uses: [0; NUMBER_OF_FACES],
+    };
+
+    /// The face which got accepted the most this run, or `None` if nothing was ever accepted
+    pub fn most_used_face(&self) -> Option<Face> {
+        ALL_FACES
+            .iter()
+            .copied()
+            .filter(|&face| self.face_uses[face as usize] > 0)
+            .max_by_key(|&face| self.face_uses[face as usize])
+    }
+
+    pub fn report(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::DiceAccepted { faces, .. } => {
+                for &face in faces {
+                    self.face_uses[face as usize] += 1;
+                }
+            }
+            GameEvent::DamageDealt(amount) => self.damage_dealt += amount,
+            GameEvent::BattleWon { .. } => self.battles_won += 1,
+            _ => {}
+        }
+    }
+}
+
+const FACE_COLUMNS: usize = 6;
+
+/// The names of the pages, which the cursor moves between
+const PAGES: [&str; 2] = ["totals", "faces"];
+const PAGES_Y: i32 = 16;
+
+fn page_x(page: usize) -> i32 {
+    80 + page as i32 * 48
+}
+
+struct StatisticsPage<'a> {
+    icons: Icons<'a>,
+    labels: Vec<TextDisplay<'a>>,
+    values: Vec<NumberDisplay<'a>>,
+}
+
+impl<'a> StatisticsPage<'a> {
+    fn new() -> Self {
+        Self {
+            icons: Icons::default(),
+            labels: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.icons.clear();
+        self.labels.clear();
+        self.values.clear();
+    }
+
+    fn show_counters(&mut self, counters: &Counters, obj: &'a ObjectController) {
+        self.clear();
+
+        let rows = [
+            ("battles won", counters.battles_won),
+            ("battles lost", counters.battles_lost),
+            ("dice rolled", counters.dice_rolled),
+            ("accepts", counters.accepts),
+            ("damage dealt", counters.damage_dealt),
+            ("damage blocked", counters.damage_blocked),
+        ];
+
+        for (i, (label, value)) in rows.iter().enumerate() {
+            let y = 36 + i as i32 * 14;
+
+            let mut label_display = TextDisplay::new((48, y).into());
+            label_display.set_text(label, obj);
+            self.labels.push(label_display);
+
+            let mut value_display = NumberDisplay::new((188, y).into());
+            value_display.set_value(Some(*value), obj);
+            self.values.push(value_display);
+        }
+    }
+
+    fn show_face_uses(&mut self, statistics: &Statistics, obj: &'a ObjectController) {
+        self.clear();
+
+        let position = |i: usize| {
+            let x = (i % FACE_COLUMNS) as i32 * 36 + 30;
+            let y = (i / FACE_COLUMNS) as i32 * 44 + 36;
+            (x, y)
+        };
+
+        self.icons.set(
+            ALL_FACES.iter().enumerate().map(|(i, &face)| {
+                let (x, y) = position(i);
+                (
+                    (x - 24 / 2, y - 24 / 2).into(),
+                    FACE_SPRITES.sprite_for_face(face),
+                )
+            }),
+            obj,
+        );
+
+        for (i, &face) in ALL_FACES.iter().enumerate() {
+            let (x, y) = position(i);
+
+            let mut value_display = NumberDisplay::new((x + 8, y + 16).into());
+            value_display.set_value(Some(statistics.face_uses(face)), obj);
+            self.values.push(value_display);
+        }
+    }
+}
+
+pub(crate) fn statistics_screen(agb: &mut Agb) {
+    let obj = &agb.obj;
+
+    let _page_names: Vec<_> = PAGES
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let mut text = TextDisplay::new((page_x(i), PAGES_Y).into());
+            text.set_text(name, obj);
+            text
+        })
+        .collect();
+
+    let mut focus = Focus::new(
+        Navigation::Row,
+        (0..PAGES.len())
+            .map(|i| (page_x(i) - 8, PAGES_Y).into())
+            .collect(),
+        CursorStyle::Pointer,
+        FocusSounds {
+            selected: None,
+            ..FocusSounds::MENU
+        },
+        obj,
+    );
+
+    let mut page = StatisticsPage::new();
+    let mut page_shown = None;
+
+    let mut input = agb::input::ButtonController::new();
+
+    loop {
+        input.update();
+
+        if focus.update(&input, &mut agb.sfx, obj) == Some(FocusEvent::Back) {
+            break;
+        }
+
+        if page_shown != Some(focus.index()) {
+            if focus.index() == 0 {
+                page.show_counters(agb.progress.statistics.counters(), obj);
+            } else {
+                page.show_face_uses(&agb.progress.statistics, obj);
+            }
+
+            page_shown = Some(focus.index());
+        }
+
+        agb.star_background.update();
+        agb.sfx.frame();
+        agb.vblank.wait_for_vblank();
+        agb.obj.commit();
+        agb.star_background.commit(&mut agb.vram);
+    }
+}
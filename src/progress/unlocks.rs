@@ -0,0 +1,68 @@
+use crate::{save, Face, ALL_FACES};
+
+use super::statistics::Statistics;
+use super::GameEvent;
+
+/// What needs to happen before a face can be offered as an upgrade
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Milestone {
+    Always,
+    ReachLevel(u32),
+    UseFace(Face, u32),
+}
+
+fn milestone_for_face(face: Face) -> Milestone {
+    match face {
+        Face::TripleShot => Milestone::ReachLevel(5),
+        Face::MalfunctionShot => Milestone::UseFace(Face::Malfunction, 30),
+        Face::DoubleShotValue => Milestone::UseFace(Face::DoubleShot, 25),
+        Face::TripleShield => Milestone::UseFace(Face::DoubleShield, 25),
+        Face::DoubleShieldValue => Milestone::ReachLevel(8),
+        Face::TripleShotValue => Milestone::ReachLevel(12),
+        Face::BurstShield => Milestone::UseFace(Face::TripleShield, 20),
+        Face::Invert => Milestone::ReachLevel(15),
+        _ => Milestone::Always,
+    }
+}
+
+pub struct FaceUnlocks {
+    unlocked: u32,
+}
+
+impl FaceUnlocks {
+    pub fn load() -> Self {
+        Self {
+            unlocked: save::load_unlocked_faces(),
+        }
+    }
+
+    pub fn is_unlocked(&self, face: Face) -> bool {
+        milestone_for_face(face) == Milestone::Always || self.unlocked & (1 << face as u32) != 0
+    }
+
+    pub fn report(&mut self, event: &GameEvent, statistics: &Statistics) {
+        let level_reached = match event {
+            GameEvent::DiceAccepted { .. } => None,
+            GameEvent::LevelReached(level) => Some(*level),
+            _ => return,
+        };
+
+        let unlocked_before = self.unlocked;
+
+        for face in ALL_FACES {
+            let is_reached = match milestone_for_face(face) {
+                Milestone::Always => false,
+                Milestone::ReachLevel(level) => level_reached.map_or(false, |l| l >= level),
+                Milestone::UseFace(used_face, uses) => statistics.face_uses(used_face) >= uses,
+            };
+
+            if is_reached {
+                self.unlocked |= 1 << face as u32;
+            }
+        }
+
+        if self.unlocked != unlocked_before {
+            save::save_unlocked_faces(self.unlocked);
+        }
+    }
+}
@@ -2,23 +2,140 @@ use agb::interrupt::free;
 use bare_metal::Mutex;
 use core::cell::RefCell;
 
+use crate::leaderboard::{LeaderboardEntry, LEADERBOARD_SIZE};
+use crate::progress::statistics::Counters;
+use crate::settings::{BattleSpeed, Settings, MAX_VOLUME};
+use crate::NUMBER_OF_FACES;
+
 const RAM_ADDRESS: *mut u8 = 0x0E00_0000 as *mut u8;
 const HIGH_SCORE_ADDRESS_START: *mut u8 = RAM_ADDRESS.wrapping_offset(1);
+const ACHIEVEMENTS_ADDRESS_START: *mut u8 = RAM_ADDRESS.wrapping_offset(5);
+const UNLOCKED_FACES_ADDRESS_START: *mut u8 = RAM_ADDRESS.wrapping_offset(9);
+const FACE_USES_ADDRESS_START: *mut u8 = RAM_ADDRESS.wrapping_offset(13);
+const LEADERBOARD_ADDRESS_START: *mut u8 = RAM_ADDRESS.wrapping_offset(81);
+const LEADERBOARD_ENTRY_SIZE: usize = 12;
+const STATISTICS_ADDRESS_START: *mut u8 = RAM_ADDRESS.wrapping_offset(201);
+const SETTINGS_ADDRESS_START: *mut u8 = RAM_ADDRESS.wrapping_offset(225);
+
+/// Stored in the first byte of save ram. Each time something new gets saved, bump this and
+/// initialise the new data in `init_save` for saves made with an older version.
+const SAVE_VERSION: u8 = 6;
 
 static HIGHSCORE: Mutex<RefCell<u32>> = Mutex::new(RefCell::new(0));
+static ACHIEVEMENTS: Mutex<RefCell<u32>> = Mutex::new(RefCell::new(0));
+static UNLOCKED_FACES: Mutex<RefCell<u32>> = Mutex::new(RefCell::new(0));
+static FACE_USES: Mutex<RefCell<[u32; NUMBER_OF_FACES]>> =
+    Mutex::new(RefCell::new([0; NUMBER_OF_FACES]));
+static LEADERBOARD: Mutex<RefCell<[LeaderboardEntry; LEADERBOARD_SIZE]>> =
+    Mutex::new(RefCell::new([LeaderboardEntry::EMPTY; LEADERBOARD_SIZE]));
+static STATISTICS: Mutex<RefCell<Counters>> = Mutex::new(RefCell::new(Counters::ZERO));
+static SETTINGS: Mutex<RefCell<Settings>> = Mutex::new(RefCell::new(Settings::DEFAULT));
+
+fn read_u32(address: *mut u8) -> u32 {
+    let mut a = [0; 4];
+    for (idx, a) in a.iter_mut().enumerate() {
+        *a = unsafe { address.add(idx).read_volatile() };
+    }
+
+    u32::from_le_bytes(a)
+}
+
+fn write_u32(address: *mut u8, value: u32) {
+    for (idx, &a) in value.to_le_bytes().iter().enumerate() {
+        unsafe { address.add(idx).write_volatile(a) };
+    }
+}
 
 pub fn init_save() {
-    if (unsafe { RAM_ADDRESS.read_volatile() } == !0) {
+    let version = unsafe { RAM_ADDRESS.read_volatile() };
+    let is_new_save = version == !0;
+
+    if is_new_save {
         save_high_score(0);
-        unsafe { RAM_ADDRESS.write_volatile(0) };
     }
 
-    let mut a = [0; 4];
-    for (idx, a) in a.iter_mut().enumerate() {
-        *a = unsafe { HIGH_SCORE_ADDRESS_START.add(idx).read_volatile() };
+    if is_new_save || version < 1 {
+        save_achievements(0);
+    }
+
+    if is_new_save || version < 2 {
+        save_unlocked_faces(0);
+        save_face_uses(&[0; NUMBER_OF_FACES]);
+    }
+
+    if is_new_save || version < 3 {
+        let mut leaderboard = [LeaderboardEntry::EMPTY; LEADERBOARD_SIZE];
+
+        // keep the best run from before there was a leaderboard, even if we don't know who it was
+        let high_score = read_u32(HIGH_SCORE_ADDRESS_START);
+        if !is_new_save && high_score > 0 && high_score <= 100 {
+            leaderboard[0] = LeaderboardEntry {
+                level: high_score,
+                time: u32::MAX,
+                initials: LeaderboardEntry::EMPTY.initials,
+            };
+        }
+
+        save_leaderboard(&leaderboard);
+    }
+
+    if is_new_save || version < 4 {
+        save_statistics(&Counters::ZERO);
+    }
+
+    if is_new_save || version < 5 {
+        save_settings(&Settings::DEFAULT);
+    }
+
+    if is_new_save || version < 6 {
+        unsafe {
+            SETTINGS_ADDRESS_START
+                .add(4)
+                .write_volatile(Settings::DEFAULT.attack_warnings as u8)
+        };
+    }
+
+    if version != SAVE_VERSION {
+        unsafe { RAM_ADDRESS.write_volatile(SAVE_VERSION) };
     }
 
-    let high_score = u32::from_le_bytes(a);
+    let high_score = read_u32(HIGH_SCORE_ADDRESS_START);
+    let achievements = read_u32(ACHIEVEMENTS_ADDRESS_START);
+    let unlocked_faces = read_u32(UNLOCKED_FACES_ADDRESS_START);
+
+    let mut face_uses = [0; NUMBER_OF_FACES];
+    for (idx, uses) in face_uses.iter_mut().enumerate() {
+        *uses = read_u32(FACE_USES_ADDRESS_START.wrapping_add(idx * 4));
+    }
+
+    let mut leaderboard = [LeaderboardEntry::EMPTY; LEADERBOARD_SIZE];
+    for (idx, entry) in leaderboard.iter_mut().enumerate() {
+        let address = LEADERBOARD_ADDRESS_START.wrapping_add(idx * LEADERBOARD_ENTRY_SIZE);
+
+        entry.level = read_u32(address);
+        entry.time = read_u32(address.wrapping_add(4));
+        for (i, initial) in entry.initials.iter_mut().enumerate() {
+            *initial = unsafe { address.add(8 + i).read_volatile() };
+        }
+    }
+
+    let statistics = Counters {
+        battles_won: read_u32(STATISTICS_ADDRESS_START),
+        battles_lost: read_u32(STATISTICS_ADDRESS_START.wrapping_add(4)),
+        dice_rolled: read_u32(STATISTICS_ADDRESS_START.wrapping_add(8)),
+        accepts: read_u32(STATISTICS_ADDRESS_START.wrapping_add(12)),
+        damage_dealt: read_u32(STATISTICS_ADDRESS_START.wrapping_add(16)),
+        damage_blocked: read_u32(STATISTICS_ADDRESS_START.wrapping_add(20)),
+    };
+
+    let setting = |idx: usize| unsafe { SETTINGS_ADDRESS_START.add(idx).read_volatile() };
+    let settings = Settings {
+        music_volume: setting(0).min(MAX_VOLUME),
+        sfx_volume: setting(1).min(MAX_VOLUME),
+        hit_effects: setting(2) != 0,
+        battle_speed: BattleSpeed::from_u8(setting(3)).unwrap_or(Settings::DEFAULT.battle_speed),
+        attack_warnings: setting(4) != 0,
+    };
 
     free(|cs| {
         if high_score > 100 {
@@ -26,6 +143,13 @@ pub fn init_save() {
         } else {
             HIGHSCORE.borrow(cs).replace(high_score);
         }
+
+        ACHIEVEMENTS.borrow(cs).replace(achievements);
+        UNLOCKED_FACES.borrow(cs).replace(unlocked_faces);
+        FACE_USES.borrow(cs).replace(face_uses);
+        LEADERBOARD.borrow(cs).replace(leaderboard);
+        STATISTICS.borrow(cs).replace(statistics);
+        SETTINGS.borrow(cs).replace(settings);
     });
 }
 
@@ -34,11 +158,225 @@ pub fn load_high_score() -> u32 {
 }
 
 pub fn save_high_score(score: u32) {
-    let a = score.to_le_bytes();
+    write_u32(HIGH_SCORE_ADDRESS_START, score);
+
+    free(|cs| HIGHSCORE.borrow(cs).replace(score));
+}
+
+pub fn load_achievements() -> u32 {
+    free(|cs| *ACHIEVEMENTS.borrow(cs).borrow())
+}
+
+pub fn save_achievements(unlocked: u32) {
+    write_u32(ACHIEVEMENTS_ADDRESS_START, unlocked);
+
+    free(|cs| ACHIEVEMENTS.borrow(cs).replace(unlocked));
+}
 
-    for (idx, &a) in a.iter().enumerate() {
-        unsafe { HIGH_SCORE_ADDRESS_START.add(idx).write_volatile(a) };
+pub fn load_unlocked_faces() -> u32 {
+    free(|cs| *UNLOCKED_FACES.borrow(cs).borrow())
+}
+
+pub fn save_unlocked_faces(unlocked: u32) {
+    write_u32(UNLOCKED_FACES_ADDRESS_START, unlocked);
+
+    free(|cs| UNLOCKED_FACES.borrow(cs).replace(unlocked));
+}
+
+pub fn load_face_uses() -> [u32; NUMBER_OF_FACES] {
+    free(|cs| *FACE_USES.borrow(cs).borrow())
+}
+
+pub fn save_face_uses(face_uses: &[u32; NUMBER_OF_FACES]) {
+    for (idx, &uses) in face_uses.iter().enumerate() {
+        write_u32(FACE_USES_ADDRESS_START.wrapping_add(idx * 4), uses);
     }
 
-    free(|cs| HIGHSCORE.borrow(cs).replace(score));
+    free(|cs| FACE_USES.borrow(cs).replace(*face_uses));
+}
+
+pub fn load_leaderboard() -> [LeaderboardEntry; LEADERBOARD_SIZE] {
+    free(|cs| *LEADERBOARD.borrow(cs).borrow())
+}
+
+pub fn save_leaderboard(leaderboard: &[LeaderboardEntry; LEADERBOARD_SIZE]) {
+    for (idx, entry) in leaderboard.iter().enumerate() {
+        let address = LEADERBOARD_ADDRESS_START.wrapping_add(idx * LEADERBOARD_ENTRY_SIZE);
+
+        write_u32(address, entry.level);
+        write_u32(address.wrapping_add(4), entry.time);
+        for (i, &initial) in entry.initials.iter().enumerate() {
+            unsafe { address.add(8 + i).write_volatile(initial) };
+        }
+    }
+
+    free(|cs| LEADERBOARD.borrow(cs).replace(*leaderboard));
+}
+
+pub fn load_statistics() -> Counters {
+    free(|cs| *STATISTICS.borrow(cs).borrow())
+}
+
+pub fn save_statistics(statistics: &Counters) {
+    write_u32(STATISTICS_ADDRESS_START, statistics.battles_won);
+    write_u32(
+        STATISTICS_ADDRESS_START.wrapping_add(4),
+        statistics.battles_lost,
+    );
+    write_u32(
+        STATISTICS_ADDRESS_START.wrapping_add(8),
+        statistics.dice_rolled,
+    );
+    write_u32(
+        STATISTICS_ADDRESS_START.wrapping_add(12),
+        statistics.accepts,
+    );
+    write_u32(
+        STATISTICS_ADDRESS_START.wrapping_add(16),
+        statistics.damage_dealt,
+    );
+    write_u32(
+        STATISTICS_ADDRESS_START.wrapping_add(20),
+        statistics.damage_blocked,
+    );
+
+    free(|cs| STATISTICS.borrow(cs).replace(*statistics));
+}
+
+pub fn load_settings() -> Settings {
+    free(|cs| *SETTINGS.borrow(cs).borrow())
+}
+
+pub fn save_settings(settings: &Settings) {
+    let bytes = [
+        settings.music_volume,
+        settings.sfx_volume,
+        settings.hit_effects as u8,
+        settings.battle_speed as u8,
+        settings.attack_warnings as u8,
+    ];
+
+    for (idx, &byte) in bytes.iter().enumerate() {
+        unsafe { SETTINGS_ADDRESS_START.add(idx).write_volatile(byte) };
+    }
+
+    free(|cs| SETTINGS.borrow(cs).replace(*settings));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One past the last byte of save ram which gets used
+    const SAVE_BYTES: usize = 230;
+
+    /// Sets save ram up like a game with `version` saved it. Ram is all ones before anything is
+    /// written to it, so that's what anything newer than `version` is left as.
+    fn old_save(version: u8, high_score: u32) {
+        for idx in 0..SAVE_BYTES {
+            unsafe { RAM_ADDRESS.add(idx).write_volatile(!0) };
+        }
+
+        unsafe { RAM_ADDRESS.write_volatile(version) };
+        write_u32(HIGH_SCORE_ADDRESS_START, high_score);
+    }
+
+    fn version() -> u8 {
+        unsafe { RAM_ADDRESS.read_volatile() }
+    }
+
+    #[test_case]
+    fn new_save_starts_empty(_gba: &mut agb::Gba) {
+        old_save(!0, !0);
+
+        init_save();
+
+        assert_eq!(version(), SAVE_VERSION);
+        assert_eq!(load_high_score(), 0);
+        assert_eq!(load_achievements(), 0);
+        assert_eq!(load_unlocked_faces(), 0);
+        assert_eq!(load_face_uses(), [0; NUMBER_OF_FACES]);
+        assert_eq!(
+            load_leaderboard(),
+            [LeaderboardEntry::EMPTY; LEADERBOARD_SIZE]
+        );
+        assert_eq!(load_statistics(), Counters::ZERO);
+        assert_eq!(load_settings(), Settings::DEFAULT);
+    }
+
+    #[test_case]
+    fn version_1_save_keeps_its_high_score_and_achievements(_gba: &mut agb::Gba) {
+        old_save(1, 12);
+        write_u32(ACHIEVEMENTS_ADDRESS_START, 0b1011);
+
+        init_save();
+
+        assert_eq!(version(), SAVE_VERSION);
+        assert_eq!(load_high_score(), 12);
+        assert_eq!(load_achievements(), 0b1011);
+
+        // everything after version 1 starts off empty
+        assert_eq!(load_unlocked_faces(), 0);
+        assert_eq!(load_face_uses(), [0; NUMBER_OF_FACES]);
+        assert_eq!(load_statistics(), Counters::ZERO);
+        assert_eq!(load_settings(), Settings::DEFAULT);
+
+        // apart from the leaderboard, which starts with the high score
+        let leaderboard = load_leaderboard();
+        assert_eq!(
+            leaderboard[0],
+            LeaderboardEntry {
+                level: 12,
+                time: u32::MAX,
+                initials: LeaderboardEntry::EMPTY.initials,
+            }
+        );
+        assert_eq!(
+            leaderboard[1..],
+            [LeaderboardEntry::EMPTY; LEADERBOARD_SIZE - 1]
+        );
+    }
+
+    #[test_case]
+    fn version_5_save_keeps_its_settings(_gba: &mut agb::Gba) {
+        old_save(5, 30);
+
+        let settings = Settings {
+            music_volume: 3,
+            sfx_volume: 5,
+            hit_effects: false,
+            battle_speed: BattleSpeed::Fast,
+            ..Settings::DEFAULT
+        };
+        save_settings(&settings);
+        // version 5 didn't have this yet, so it's still all ones
+        unsafe { SETTINGS_ADDRESS_START.add(4).write_volatile(!0) };
+
+        init_save();
+
+        assert_eq!(version(), SAVE_VERSION);
+        assert_eq!(load_high_score(), 30);
+        assert_eq!(load_settings(), settings);
+    }
+
+    #[test_case]
+    fn current_save_is_left_alone(_gba: &mut agb::Gba) {
+        old_save(!0, !0);
+        init_save();
+
+        let settings = Settings {
+            attack_warnings: true,
+            ..Settings::DEFAULT
+        };
+        save_high_score(7);
+        save_achievements(0b110);
+        save_settings(&settings);
+
+        init_save();
+
+        assert_eq!(version(), SAVE_VERSION);
+        assert_eq!(load_high_score(), 7);
+        assert_eq!(load_achievements(), 0b110);
+        assert_eq!(load_settings(), settings);
+    }
 }
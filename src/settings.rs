@@ -0,0 +1,250 @@
+use agb::fixnum::Num;
+use agb::input::{Button, Tri};
+use alloc::vec::Vec;
+
+use crate::graphics::{NumberDisplay, TextDisplay};
+use crate::sfx::SoundEvent;
+use crate::ui::{CursorStyle, Focus, FocusEvent, FocusSounds, Navigation};
+use crate::{save, Agb};
+
+mod jukebox;
+
+pub const MAX_VOLUME: u8 = 8;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BattleSpeed {
+    Slow,
+    Normal,
+    Fast,
+}
+
+impl BattleSpeed {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(BattleSpeed::Slow),
+            1 => Some(BattleSpeed::Normal),
+            2 => Some(BattleSpeed::Fast),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            BattleSpeed::Slow => "slow",
+            BattleSpeed::Normal => "normal",
+            BattleSpeed::Fast => "fast",
+        }
+    }
+
+    fn change(self, direction: Tri) -> Self {
+        let value = (self as u8 as i8 + direction as i8).clamp(0, 2);
+        Self::from_u8(value as u8).unwrap_or(self)
+    }
+
+    /// How many steps a battle takes on this frame. The animations, dice rolls and enemy
+    /// attacks all move on by this many steps, so the whole battle speeds up or slows down.
+    pub fn steps_for_frame(self, frame: usize) -> usize {
+        match self {
+            BattleSpeed::Slow => frame % 2,
+            BattleSpeed::Normal => 1,
+            BattleSpeed::Fast => 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Settings {
+    /// Between 0 and `MAX_VOLUME` inclusive
+    pub music_volume: u8,
+    /// Between 0 and `MAX_VOLUME` inclusive
+    pub sfx_volume: u8,
+    /// Shake the screen and flash ships when they get hit. Both go together behind one setting for
+    /// players who are sensitive to motion or flashing.
+    pub hit_effects: bool,
+    pub battle_speed: BattleSpeed,
+    /// Tick faster and faster as an enemy attack is about to happen
+    pub attack_warnings: bool,
+}
+
+impl Settings {
+    pub const DEFAULT: Self = Self {
+        music_volume: MAX_VOLUME,
+        sfx_volume: MAX_VOLUME,
+        hit_effects: true,
+        battle_speed: BattleSpeed::Normal,
+        attack_warnings: false,
+    };
+}
+
+pub fn volume_for_setting(setting: u8) -> Num<i16, 4> {
+    Num::new(setting.min(MAX_VOLUME) as i16) / MAX_VOLUME as i16
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum SettingsRow {
+    MusicVolume,
+    SfxVolume,
+    HitEffects,
+    BattleSpeed,
+    AttackWarnings,
+    Jukebox,
+}
+
+const SETTINGS_ROWS: [SettingsRow; 6] = [
+    SettingsRow::MusicVolume,
+    SettingsRow::SfxVolume,
+    SettingsRow::HitEffects,
+    SettingsRow::BattleSpeed,
+    SettingsRow::AttackWarnings,
+    SettingsRow::Jukebox,
+];
+
+impl SettingsRow {
+    fn name(self) -> &'static str {
+        match self {
+            SettingsRow::MusicVolume => "music",
+            SettingsRow::SfxVolume => "sound effects",
+            SettingsRow::HitEffects => "hit effects",
+            SettingsRow::BattleSpeed => "battle speed",
+            SettingsRow::AttackWarnings => "attack warnings",
+            SettingsRow::Jukebox => "jukebox",
+        }
+    }
+
+    fn change(self, settings: &mut Settings, direction: Tri) {
+        let change_volume =
+            |volume: u8| (volume as i8 + direction as i8).clamp(0, MAX_VOLUME as i8) as u8;
+
+        match self {
+            SettingsRow::MusicVolume => {
+                settings.music_volume = change_volume(settings.music_volume)
+            }
+            SettingsRow::SfxVolume => settings.sfx_volume = change_volume(settings.sfx_volume),
+            SettingsRow::HitEffects => settings.hit_effects = !settings.hit_effects,
+            SettingsRow::BattleSpeed => {
+                settings.battle_speed = settings.battle_speed.change(direction)
+            }
+            SettingsRow::AttackWarnings => settings.attack_warnings = !settings.attack_warnings,
+            SettingsRow::Jukebox => {}
+        }
+    }
+}
+
+const SETTINGS_X: i32 = 48;
+const SETTINGS_VALUE_X: i32 = 160;
+const SETTINGS_Y: i32 = 52;
+
+pub(crate) fn settings_screen(agb: &mut Agb) {
+    let mut cursor = 0;
+
+    loop {
+        let open_jukebox = {
+            let obj = &agb.obj;
+
+            let _labels: Vec<_> = SETTINGS_ROWS
+                .iter()
+                .enumerate()
+                .map(|(i, row)| {
+                    let mut label =
+                        TextDisplay::new((SETTINGS_X, SETTINGS_Y + i as i32 * 12).into());
+                    label.set_text(row.name(), obj);
+                    label
+                })
+                .collect();
+
+            let mut music_volume = NumberDisplay::new((SETTINGS_VALUE_X + 4, SETTINGS_Y).into());
+            let mut sfx_volume = NumberDisplay::new((SETTINGS_VALUE_X + 4, SETTINGS_Y + 12).into());
+            let mut hit_effects = TextDisplay::new((SETTINGS_VALUE_X, SETTINGS_Y + 24).into());
+            let mut battle_speed = TextDisplay::new((SETTINGS_VALUE_X, SETTINGS_Y + 36).into());
+            let mut attack_warnings = TextDisplay::new((SETTINGS_VALUE_X, SETTINGS_Y + 48).into());
+
+            let mut focus = Focus::new(
+                Navigation::Column,
+                (0..SETTINGS_ROWS.len())
+                    .map(|i| (SETTINGS_X - 8, SETTINGS_Y + i as i32 * 12).into())
+                    .collect(),
+                CursorStyle::Pointer,
+                FocusSounds {
+                    selected: None,
+                    ..FocusSounds::MENU
+                },
+                obj,
+            );
+            focus.set_index(cursor);
+
+            let mut input = agb::input::ButtonController::new();
+            let mut settings_changed = true;
+
+            loop {
+                input.update();
+
+                let lr: Tri = (
+                    input.is_just_pressed(Button::LEFT),
+                    input.is_just_pressed(Button::RIGHT),
+                )
+                    .into();
+
+                let event = focus.update(&input, &mut agb.sfx, obj);
+                cursor = focus.index();
+
+                if lr != Tri::Zero && SETTINGS_ROWS[cursor] != SettingsRow::Jukebox {
+                    SETTINGS_ROWS[cursor].change(&mut agb.settings, lr);
+                    agb.sfx.apply_settings(&agb.settings);
+                    settings_changed = true;
+
+                    agb.sfx.play(SoundEvent::MoveCursor);
+                }
+
+                if settings_changed {
+                    music_volume.set_value(Some(agb.settings.music_volume as u32), obj);
+                    sfx_volume.set_value(Some(agb.settings.sfx_volume as u32), obj);
+                    hit_effects.set_text(
+                        if agb.settings.hit_effects {
+                            "on"
+                        } else {
+                            "off"
+                        },
+                        obj,
+                    );
+                    battle_speed.set_text(agb.settings.battle_speed.name(), obj);
+                    attack_warnings.set_text(
+                        if agb.settings.attack_warnings {
+                            "on"
+                        } else {
+                            "off"
+                        },
+                        obj,
+                    );
+
+                    settings_changed = false;
+                }
+
+                if event == Some(FocusEvent::Selected(cursor))
+                    && SETTINGS_ROWS[cursor] == SettingsRow::Jukebox
+                {
+                    agb.sfx.play(SoundEvent::Select);
+                    break true;
+                }
+
+                if event == Some(FocusEvent::Back) {
+                    save::save_settings(&agb.settings);
+                    break false;
+                }
+
+                agb.star_background.update();
+                agb.sfx.frame();
+                agb.vblank.wait_for_vblank();
+                agb.obj.commit();
+                agb.star_background.commit(&mut agb.vram);
+            }
+        };
+
+        agb.obj.commit();
+
+        if open_jukebox {
+            jukebox::jukebox_screen(agb);
+        } else {
+            break;
+        }
+    }
+}
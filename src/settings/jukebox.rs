@@ -0,0 +1,149 @@
+use agb::fixnum::Vector2D;
+use agb::input::Button;
+use alloc::vec::Vec;
+
+use crate::graphics::{FractionDisplay, TextDisplay};
+use crate::sfx::{Clip, ALL_CLIPS, SAMPLE_RATE};
+use crate::ui::{CursorStyle, Focus, FocusEvent, FocusSounds, Navigation};
+use crate::Agb;
+
+const VISIBLE_ROWS: usize = 5;
+const LIST_X: i32 = 56;
+const LIST_Y: i32 = 28;
+const ROW_HEIGHT: i32 = 10;
+const DETAILS_Y: i32 = LIST_Y + VISIBLE_ROWS as i32 * ROW_HEIGHT + 8;
+
+fn length_in_seconds(clip: Clip) -> usize {
+    (clip.samples() + SAMPLE_RATE - 1) / SAMPLE_RATE
+}
+
+/// Where the cursor goes for every clip, with the list scrolled down by `scroll` rows. The clips
+/// which are scrolled out of view get positions off the list, but the cursor is never on them.
+fn cursor_positions(scroll: usize) -> Vec<Vector2D<i32>> {
+    (0..ALL_CLIPS.len())
+        .map(|i| (LIST_X - 8, LIST_Y + (i as i32 - scroll as i32) * ROW_HEIGHT).into())
+        .collect()
+}
+
+/// Lists every clip in the game and lets them be played one at a time
+pub(super) fn jukebox_screen(agb: &mut Agb) {
+    let obj = &agb.obj;
+
+    agb.sfx.set_music_muted(true);
+
+    let mut title = TextDisplay::new((LIST_X + 32, 12).into());
+    title.set_text("jukebox", obj);
+
+    let mut rows: Vec<TextDisplay> = (0..VISIBLE_ROWS)
+        .map(|i| TextDisplay::new((LIST_X, LIST_Y + i as i32 * ROW_HEIGHT).into()))
+        .collect();
+
+    let mut focus = Focus::new(
+        Navigation::Column,
+        cursor_positions(0),
+        CursorStyle::Pointer,
+        FocusSounds {
+            selected: None,
+            ..FocusSounds::MENU
+        },
+        obj,
+    );
+
+    let mut time_label = TextDisplay::new((LIST_X, DETAILS_Y).into());
+    time_label.set_text("time", obj);
+    let mut time = FractionDisplay::new((LIST_X + 24, DETAILS_Y).into(), 3, obj);
+
+    let mut loop_label = TextDisplay::new((LIST_X, DETAILS_Y + ROW_HEIGHT).into());
+    loop_label.set_text("loop", obj);
+    let mut loop_value = TextDisplay::new((LIST_X + 24, DETAILS_Y + ROW_HEIGHT).into());
+
+    let mut help = TextDisplay::new((LIST_X, DETAILS_Y + 3 * ROW_HEIGHT).into());
+    help.set_text("a:play start:loop", obj);
+
+    let mut input = agb::input::ButtonController::new();
+    let mut scroll = 0;
+    let mut list_changed = true;
+
+    let mut should_loop = false;
+    let mut loop_changed = true;
+
+    // the index of the clip which is playing
+    let mut playing: Option<usize> = None;
+
+    loop {
+        input.update();
+
+        let event = focus.update(&input, &mut agb.sfx, obj);
+        let selected = focus.index();
+
+        if selected < scroll {
+            scroll = selected;
+            list_changed = true;
+        } else if selected >= scroll + VISIBLE_ROWS {
+            scroll = selected + 1 - VISIBLE_ROWS;
+            list_changed = true;
+        }
+
+        if event == Some(FocusEvent::Selected(selected)) {
+            if playing == Some(selected) {
+                agb.sfx.stop_clip();
+                playing = None;
+            } else {
+                agb.sfx.play_clip(ALL_CLIPS[selected].1, should_loop, 0);
+                playing = Some(selected);
+            }
+        }
+
+        if input.is_just_pressed(Button::START) {
+            should_loop = !should_loop;
+            loop_changed = true;
+
+            // restart whatever is playing from the same place so the new setting takes effect
+            if let Some(index) = playing {
+                if let Some(position) = agb.sfx.clip_position() {
+                    agb.sfx.play_clip(ALL_CLIPS[index].1, should_loop, position);
+                }
+            }
+        }
+
+        if event == Some(FocusEvent::Back) {
+            agb.sfx.stop_clip();
+            agb.sfx.set_music_muted(false);
+            break;
+        }
+
+        let position = agb.sfx.clip_position();
+
+        if position.is_none() {
+            playing = None;
+        }
+
+        let shown_clip = playing.unwrap_or(selected);
+        time.set_value(
+            position.map_or(0, |position| position / SAMPLE_RATE),
+            length_in_seconds(ALL_CLIPS[shown_clip].1),
+            obj,
+        );
+
+        if list_changed {
+            for (i, row) in rows.iter_mut().enumerate() {
+                let name = ALL_CLIPS.get(scroll + i).map_or("", |(name, _)| *name);
+                row.set_text(name, obj);
+            }
+
+            focus.set_positions(cursor_positions(scroll));
+            list_changed = false;
+        }
+
+        if loop_changed {
+            loop_value.set_text(if should_loop { "on" } else { "off" }, obj);
+            loop_changed = false;
+        }
+
+        agb.star_background.update();
+        agb.sfx.frame();
+        agb.vblank.wait_for_vblank();
+        agb.obj.commit();
+        agb.star_background.commit(&mut agb.vram);
+    }
+}
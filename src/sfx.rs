@@ -1,41 +1,135 @@
-use agb::fixnum::num;
-use agb::sound::mixer::{ChannelId, Mixer, SoundChannel};
-use agb::{include_wav, rng};
-
-const DICE_ROLLS: &[&[u8]] = &[
-    include_wav!("sfx/SingleRoll_1.wav"),
-    include_wav!("sfx/SingleRoll_2.wav"),
-    include_wav!("sfx/SingleRoll_3.wav"),
-    include_wav!("sfx/SingleRoll_4.wav"),
-    include_wav!("sfx/SingleRoll_5.wav"),
-];
+use agb::fixnum::{num, Num};
+use agb::rng;
+use agb::sound::mixer::{Mixer, SoundChannel};
+
+use crate::settings::{volume_for_setting, Settings};
+
+mod adpcm;
+mod bank;
+mod voices;
+
+use adpcm::{AdpcmTrack, Stream, StreamPool};
+pub use bank::SoundEvent;
+pub use voices::VoiceId;
+use voices::VoiceManager;
+
+// All the audio is encoded by build.rs and streamed in as it plays, see `adpcm`
+static MENU_BGM: AdpcmTrack =
+    AdpcmTrack::new(include_bytes!(concat!(env!("OUT_DIR"), "/BGM_Menu.adpcm")));
+static BATTLE_BGM: AdpcmTrack =
+    AdpcmTrack::new(include_bytes!(concat!(env!("OUT_DIR"), "/BGM_Fight.adpcm")));
+static TITLE_BGM: AdpcmTrack =
+    AdpcmTrack::new(include_bytes!(concat!(env!("OUT_DIR"), "/BGM_Title.adpcm")));
+
+/// The mixer runs at this frequency
+pub const SAMPLE_RATE: usize = 32768;
+
+#[derive(Clone, Copy)]
+pub struct Clip(&'static AdpcmTrack);
+
+impl Clip {
+    pub fn samples(self) -> usize {
+        self.0.samples()
+    }
+}
 
-const MULTI_ROLLS: &[&[u8]] = &[
-    include_wav!("sfx/MultiRoll_1.wav"),
-    include_wav!("sfx/MultiRoll_2.wav"),
-    include_wav!("sfx/MultiRoll_3.wav"),
-    include_wav!("sfx/MultiRoll_4.wav"),
-    include_wav!("sfx/MultiRoll_5.wav"),
+/// Every clip in the game along with a name, for the jukebox
+pub static ALL_CLIPS: &[(&str, Clip)] = &[
+    ("title music", Clip(&TITLE_BGM)),
+    ("menu music", Clip(&MENU_BGM)),
+    ("battle music", Clip(&BATTLE_BGM)),
+    ("roll 1", Clip(bank::DICE_ROLLS[0])),
+    ("roll 2", Clip(bank::DICE_ROLLS[1])),
+    ("roll 3", Clip(bank::DICE_ROLLS[2])),
+    ("roll 4", Clip(bank::DICE_ROLLS[3])),
+    ("roll 5", Clip(bank::DICE_ROLLS[4])),
+    ("multi roll 1", Clip(bank::MULTI_ROLLS[0])),
+    ("multi roll 2", Clip(bank::MULTI_ROLLS[1])),
+    ("multi roll 3", Clip(bank::MULTI_ROLLS[2])),
+    ("multi roll 4", Clip(bank::MULTI_ROLLS[3])),
+    ("multi roll 5", Clip(bank::MULTI_ROLLS[4])),
+    ("shoot", Clip(bank::SHOOT[0])),
+    ("shot hit", Clip(bank::SHOT_HIT[0])),
+    ("ship explode", Clip(bank::SHIP_EXPLODE[0])),
+    ("move cursor", Clip(bank::MOVE_CURSOR[0])),
+    ("select", Clip(bank::SELECT[0])),
+    ("back", Clip(bank::BACK[0])),
+    ("accept", Clip(bank::ACCEPT[0])),
+    ("shield down", Clip(bank::SHIELD_DOWN[0])),
+    ("shield up", Clip(bank::SHIELD_UP[0])),
+    ("shield defend", Clip(bank::SHIELD_DEFEND[0])),
+    ("disrupt", Clip(bank::DISRUPT[0])),
+    ("heal", Clip(bank::HEAL[0])),
+    ("send burst", Clip(bank::SEND_BURST_SHIELD[0])),
+    ("burst hit", Clip(bank::BURST_SHIELD_HIT[0])),
+    ("warning tick", Clip(bank::WARNING_TICK[0])),
 ];
 
-const MENU_BGM: &[u8] = include_wav!("sfx/BGM_Menu.wav");
-const BATTLE_BGM: &[u8] = include_wav!("sfx/BGM_Fight.wav");
-const TITLE_BGM: &[u8] = include_wav!("sfx/BGM_Title.wav");
-
-const SHOOT: &[u8] = include_wav!("sfx/shoot.wav");
-const SHOT_HIT: &[u8] = include_wav!("sfx/shot_hit.wav");
-const SHIP_EXPLODE: &[u8] = include_wav!("sfx/ship_explode.wav");
-const MOVE_CURSOR: &[u8] = include_wav!("sfx/move_cursor.wav");
-const SELECT: &[u8] = include_wav!("sfx/select.wav");
-const BACK: &[u8] = include_wav!("sfx/back.wav");
-const ACCEPT: &[u8] = include_wav!("sfx/accept.wav");
-const SHIELD_DOWN: &[u8] = include_wav!("sfx/shield_down.wav");
-const SHIELD_UP: &[u8] = include_wav!("sfx/shield_up.wav");
-const SHIELD_DEFEND: &[u8] = include_wav!("sfx/shield_defend.wav");
-const DISRUPT: &[u8] = include_wav!("sfx/disrupt.wav");
-const HEAL: &[u8] = include_wav!("sfx/heal.wav");
-const SEND_BURST_SHIELD: &[u8] = include_wav!("sfx/send_burst_shield.wav");
-const BURST_SHIELD_HIT: &[u8] = include_wav!("sfx/burst_shield_hit.wav");
+/// How many frames it takes for one piece of background music to fade into the next
+const CROSSFADE_FRAMES: i16 = 32;
+/// How many frames it takes for the battle intensity layer to fully fade in or out
+const INTENSITY_FADE_FRAMES: i16 = 48;
+/// The battle music's tempo. It starts on a beat, and the intensity layer hits on every beat.
+const BATTLE_MUSIC_BPM: usize = 100;
+/// How many samples before a beat its hit gets started. It has to be more than the mixer plays in
+/// a frame so that no beat gets missed.
+const INTENSITY_LOOKAHEAD: usize = 1024;
+
+/// The sample of the battle music that `beat` starts on
+fn battle_beat_start(beat: usize) -> usize {
+    beat * SAMPLE_RATE * 60 / BATTLE_MUSIC_BPM
+}
+
+/// What a track should do when it starts playing
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TrackStart {
+    /// Always start from the beginning
+    Restart,
+    /// Pick up at the position the previous track got to, if that track also resumes. The
+    /// menu and battle music are the same length so they can follow on from each other.
+    Resume,
+}
+
+/// Which ship a sound comes from, so it can be panned towards that side of the screen
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Side {
+    Player,
+    Enemy,
+}
+
+impl Side {
+    fn panning(self) -> Num<i16, 4> {
+        match self {
+            Side::Player => num!(-0.5),
+            Side::Enemy => num!(0.5),
+        }
+    }
+}
+
+/// Pans fully left at the left edge of the screen and fully right at the right edge
+pub fn panning_for_x(x: i32) -> Num<i16, 4> {
+    let half_width = 240 / 2;
+    Num::new(x.clamp(0, 2 * half_width) as i16 - half_width as i16) / half_width as i16
+}
+
+/// A piece of background music along with how it starts
+struct Bgm {
+    track: &'static AdpcmTrack,
+    start: TrackStart,
+}
+
+static TITLE_MUSIC: Bgm = Bgm {
+    track: &TITLE_BGM,
+    start: TrackStart::Restart,
+};
+static MENU_MUSIC: Bgm = Bgm {
+    track: &MENU_BGM,
+    start: TrackStart::Resume,
+};
+static BATTLE_MUSIC: Bgm = Bgm {
+    track: &BATTLE_BGM,
+    start: TrackStart::Resume,
+};
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum BattleOrMenu {
@@ -44,160 +138,349 @@ enum BattleOrMenu {
     Title,
 }
 
+impl BattleOrMenu {
+    fn bgm(self) -> &'static Bgm {
+        match self {
+            BattleOrMenu::Battle => &BATTLE_MUSIC,
+            BattleOrMenu::Menu => &MENU_MUSIC,
+            BattleOrMenu::Title => &TITLE_MUSIC,
+        }
+    }
+}
+
 pub struct Sfx<'a> {
     mixer: &'a mut Mixer,
     state: BattleOrMenu,
-
-    current_bgm: ChannelId,
+    streams: StreamPool,
+
+    current_bgm: Option<Stream>,
+    fading_bgm: Option<Stream>,
+    /// How many frames are left of the current crossfade, 0 if there isn't one
+    crossfade_frames: i16,
+
+    /// The most recent hit of the intensity layer, which is the shot hit sound played lower down
+    /// on each beat of the battle music like a kick drum. It uses what's already in the ROM
+    /// rather than a whole second copy of the music.
+    intensity_hit: Option<Stream>,
+    /// The beat `intensity_hit` was started for
+    intensity_beat: Option<usize>,
+    /// Between 0 and `INTENSITY_FADE_FRAMES`
+    intensity: i16,
+    in_danger: bool,
+
+    music_volume: Num<i16, 4>,
+    sfx_volume: Num<i16, 4>,
+    music_muted: bool,
+
+    voices: VoiceManager,
+    jukebox_clip: Option<Stream>,
 }
 
 impl<'a> Sfx<'a> {
     pub fn new(mixer: &'a mut Mixer) -> Self {
-        let mut title_music = SoundChannel::new_high_priority(TITLE_BGM);
-        title_music.should_loop();
-        let title_channel = mixer.play_sound(title_music).unwrap();
-
-        Self {
+        let mut sfx = Self {
             mixer,
             state: BattleOrMenu::Title,
+            streams: StreamPool::default(),
 
-            current_bgm: title_channel,
-        }
+            current_bgm: None,
+            fading_bgm: None,
+            crossfade_frames: 0,
+
+            intensity_hit: None,
+            intensity_beat: None,
+            intensity: 0,
+            in_danger: false,
+
+            music_volume: Num::new(1),
+            sfx_volume: Num::new(1),
+            music_muted: false,
+
+            voices: VoiceManager::default(),
+            jukebox_clip: None,
+        };
+
+        sfx.current_bgm = sfx
+            .streams
+            .play(sfx.mixer, BattleOrMenu::Title.bgm().track, 0, true);
+        sfx
+    }
+
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        self.music_volume = volume_for_setting(settings.music_volume);
+        self.sfx_volume = volume_for_setting(settings.sfx_volume);
+
+        self.update_music_volume();
+    }
+
+    /// Should be called every frame during a battle to fade the intensity layer in or out
+    pub fn set_danger(&mut self, in_danger: bool) {
+        self.in_danger = in_danger;
     }
 
     pub fn frame(&mut self) {
+        let mut volume_changed = false;
+
+        if self.crossfade_frames > 0 {
+            self.crossfade_frames -= 1;
+
+            if self.crossfade_frames == 0 {
+                self.stop_fading_bgm();
+
+                if self.state != BattleOrMenu::Battle {
+                    self.stop_intensity_hit();
+                }
+            }
+
+            volume_changed = true;
+        }
+
+        let target_intensity = if self.in_danger {
+            INTENSITY_FADE_FRAMES
+        } else {
+            0
+        };
+
+        if self.intensity != target_intensity {
+            self.intensity += if self.in_danger { 1 } else { -1 };
+            volume_changed = true;
+        }
+
+        if volume_changed {
+            self.update_music_volume();
+        }
+
+        // the music goes first so that it's never the one which has to wait to be decoded
+        self.streams.frame();
+        self.update_streams();
+        self.update_intensity_layer();
+        self.voices.frame(self.mixer, &mut self.streams);
         self.mixer.frame();
     }
 
-    pub fn battle(&mut self) {
-        if self.state == BattleOrMenu::Battle {
-            return;
-        }
+    fn update_streams(&mut self) {
+        let streams = &mut self.streams;
+        let mixer = &mut *self.mixer;
 
-        self.state = BattleOrMenu::Battle;
+        for music in [&mut self.current_bgm, &mut self.fading_bgm] {
+            if let Some(stream) = music {
+                streams.update(mixer, stream);
+            }
+        }
 
-        let current_channel = self.mixer.channel(&self.current_bgm).unwrap();
-        let pos = current_channel.pos();
-        current_channel.stop();
+        if let Some(stream) = &mut self.intensity_hit {
+            if !self.streams.update(self.mixer, stream) {
+                self.stop_intensity_hit();
+            }
+        }
 
-        let mut battle_music = SoundChannel::new_high_priority(BATTLE_BGM);
-        battle_music.should_loop().set_pos(pos);
-        self.current_bgm = self.mixer.play_sound(battle_music).unwrap();
+        if let Some(stream) = &mut self.jukebox_clip {
+            if !self.streams.update(self.mixer, stream) {
+                self.stop_clip();
+            }
+        }
     }
 
-    pub fn customise(&mut self) {
-        if self.state == BattleOrMenu::Menu {
+    /// Starts the intensity layer's next hit if a beat of the battle music is coming up. It gets
+    /// started with just enough silence in front of it to land exactly on the beat.
+    fn update_intensity_layer(&mut self) {
+        if self.state != BattleOrMenu::Battle || self.intensity == 0 {
             return;
         }
 
-        let should_restart = self.state == BattleOrMenu::Title;
+        let position = match &self.current_bgm {
+            Some(battle_bgm) => battle_bgm.position(),
+            None => return,
+        };
 
-        self.state = BattleOrMenu::Menu;
-        let current_channel = self.mixer.channel(&self.current_bgm).unwrap();
-        let pos = current_channel.pos();
-        current_channel.stop();
+        let next_beat = position * BATTLE_MUSIC_BPM / (SAMPLE_RATE * 60) + 1;
+        let next_beat_start = battle_beat_start(next_beat);
 
-        let mut menu_music = SoundChannel::new_high_priority(MENU_BGM);
-        menu_music
-            .should_loop()
-            .set_pos(if should_restart { 0.into() } else { pos });
-        self.current_bgm = self.mixer.play_sound(menu_music).unwrap();
-    }
+        // the music loops back round to its first beat
+        let (next_beat, next_beat_start) = if next_beat_start >= BATTLE_BGM.samples() {
+            (0, BATTLE_BGM.samples())
+        } else {
+            (next_beat, next_beat_start)
+        };
 
-    pub fn title_screen(&mut self) {
-        if self.state == BattleOrMenu::Title {
+        let delay = next_beat_start - position;
+        if delay > INTENSITY_LOOKAHEAD || self.intensity_beat == Some(next_beat) {
             return;
         }
 
-        self.state = BattleOrMenu::Title;
-        self.mixer.channel(&self.current_bgm).unwrap().stop();
+        self.intensity_beat = Some(next_beat);
+        self.stop_intensity_hit();
 
-        let mut title_music = SoundChannel::new_high_priority(TITLE_BGM);
-        title_music.should_loop();
-        self.current_bgm = self.mixer.play_sound(title_music).unwrap();
-    }
+        // the silence gets played at the same speed as the hit
+        let speed: Num<usize, 8> = num!(0.75);
+        let delay = (Num::new(delay) * speed).floor();
 
-    pub fn roll(&mut self) {
-        let roll_sound_to_use = rng::gen().rem_euclid(DICE_ROLLS.len() as i32);
-        let sound_channel = SoundChannel::new(DICE_ROLLS[roll_sound_to_use as usize]);
+        self.intensity_hit =
+            self.streams
+                .play_after(self.mixer, bank::SHOT_HIT[0], delay, |data| {
+                    let mut channel = SoundChannel::new_high_priority(data);
+                    channel.playback(speed);
+                    channel
+                });
+
+        self.update_music_volume();
+    }
 
-        self.mixer.play_sound(sound_channel);
+    /// Silences the background music without stopping it, so it carries on where it was
+    pub fn set_music_muted(&mut self, muted: bool) {
+        self.music_muted = muted;
+        self.update_music_volume();
     }
 
-    pub fn roll_multi(&mut self) {
-        let roll_sound_to_use = rng::gen().rem_euclid(MULTI_ROLLS.len() as i32);
-        let sound_channel = SoundChannel::new(MULTI_ROLLS[roll_sound_to_use as usize]);
+    fn update_music_volume(&mut self) {
+        let music_volume = if self.music_muted {
+            Num::new(0)
+        } else {
+            self.music_volume
+        };
+
+        let fade_out_volume = music_volume * self.crossfade_frames / CROSSFADE_FRAMES;
+        let fade_in_volume = music_volume - fade_out_volume;
+
+        if let Some(current_bgm) = &self.current_bgm {
+            if let Some(channel) = current_bgm.channel(self.mixer) {
+                channel.volume(fade_in_volume);
+            }
+        }
+
+        if let Some(fading_bgm) = &self.fading_bgm {
+            if let Some(channel) = fading_bgm.channel(self.mixer) {
+                channel.volume(fade_out_volume);
+            }
+        }
 
-        self.mixer.play_sound(sound_channel);
+        // the intensity layer follows the battle music in and out of crossfades
+        let battle_volume = if self.state == BattleOrMenu::Battle {
+            fade_in_volume
+        } else {
+            fade_out_volume
+        };
+
+        if let Some(intensity_hit) = &self.intensity_hit {
+            if let Some(channel) = intensity_hit.channel(self.mixer) {
+                channel.volume(battle_volume * self.intensity / INTENSITY_FADE_FRAMES);
+            }
+        }
     }
 
-    pub fn shoot(&mut self) {
-        self.mixer.play_sound(SoundChannel::new(SHOOT));
+    fn stop_intensity_hit(&mut self) {
+        if let Some(intensity_hit) = self.intensity_hit.take() {
+            self.streams.stop(self.mixer, intensity_hit);
+        }
     }
 
-    pub fn shot_hit(&mut self) {
-        self.mixer.play_sound(SoundChannel::new(SHOT_HIT));
+    fn stop_fading_bgm(&mut self) {
+        if let Some(fading_bgm) = self.fading_bgm.take() {
+            self.streams.stop(self.mixer, fading_bgm);
+        }
     }
 
-    pub fn ship_explode(&mut self) {
-        self.mixer.play_sound(SoundChannel::new(SHIP_EXPLODE));
+    pub fn play(&mut self, event: SoundEvent) -> Option<VoiceId> {
+        let entry = bank::entry_for(event);
+
+        let clip = entry.clips[rng::gen().rem_euclid(entry.clips.len() as i32) as usize];
+        let panning = event.side().map_or(0.into(), Side::panning);
+
+        let variation = entry.pitch_variation;
+        let playback =
+            entry.pitch - variation + variation * 2 * rng::gen().rem_euclid(256) as usize / 256;
+
+        let volume_variation = entry.volume_variation;
+        let volume = entry.volume
+            * (Num::new(1) - volume_variation
+                + volume_variation * 2 * rng::gen().rem_euclid(256) as i16 / 256)
+            * self.sfx_volume;
+
+        self.voices.play(
+            self.mixer,
+            &mut self.streams,
+            entry.clips,
+            clip,
+            entry.category,
+            |data| {
+                let mut channel = SoundChannel::new(data);
+                channel.volume(volume).panning(panning).playback(playback);
+                channel
+            },
+        )
     }
 
-    pub fn move_cursor(&mut self) {
-        let mut channel = SoundChannel::new(MOVE_CURSOR);
-        channel.volume(num!(0.5));
+    /// Plays a clip on its own at full volume for the jukebox, replacing whatever it was
+    /// playing before
+    pub fn play_clip(&mut self, clip: Clip, should_loop: bool, position: usize) {
+        self.stop_clip();
+        self.jukebox_clip = self.streams.play(self.mixer, clip.0, position, should_loop);
+    }
 
-        self.mixer.play_sound(channel);
+    /// How many samples into the jukebox clip playback has got, or `None` if it has finished
+    pub fn clip_position(&mut self) -> Option<usize> {
+        self.jukebox_clip.as_ref().map(Stream::position)
     }
 
-    pub fn select(&mut self) {
-        let mut channel = SoundChannel::new(SELECT);
-        channel.volume(num!(0.75));
+    pub fn stop_clip(&mut self) {
+        if let Some(stream) = self.jukebox_clip.take() {
+            self.streams.stop(self.mixer, stream);
+        }
+    }
 
-        self.mixer.play_sound(channel);
+    /// Moves a sound which is still playing, for things which travel across the screen
+    pub fn set_panning(&mut self, voice: VoiceId, panning: Num<i16, 4>) {
+        if let Some(channel) = self.voices.channel(self.mixer, voice) {
+            channel.panning(panning);
+        }
     }
 
-    pub fn back(&mut self) {
-        let mut channel = SoundChannel::new(BACK);
-        channel.volume(num!(0.5));
+    fn crossfade_to(&mut self, state: BattleOrMenu) {
+        if self.state == state {
+            return;
+        }
 
-        self.mixer.play_sound(channel);
-    }
+        let previous_state = self.state;
+        self.state = state;
 
-    pub fn accept(&mut self) {
-        let mut channel = SoundChannel::new(ACCEPT);
-        channel.volume(num!(0.5));
+        // if we're already in the middle of a crossfade, the quieter track gets cut off
+        self.stop_fading_bgm();
+        if previous_state != BattleOrMenu::Battle {
+            self.stop_intensity_hit();
+        }
 
-        self.mixer.play_sound(channel);
-    }
+        let bgm = state.bgm();
+        let position = self.current_bgm.as_ref().map_or(0, Stream::position);
 
-    pub fn shield_down(&mut self) {
-        self.mixer.play_sound(SoundChannel::new(SHIELD_DOWN));
-    }
+        let should_resume =
+            bgm.start == TrackStart::Resume && previous_state.bgm().start == TrackStart::Resume;
 
-    pub fn shield_up(&mut self) {
-        self.mixer.play_sound(SoundChannel::new(SHIELD_UP));
-    }
+        let start_position = if should_resume { position } else { 0 };
 
-    pub fn shield_defend(&mut self) {
-        let mut channel = SoundChannel::new(SHIELD_DEFEND);
-        channel.volume(num!(0.5));
-        self.mixer.play_sound(channel);
-    }
+        let music = self
+            .streams
+            .play(self.mixer, bgm.track, start_position, true);
+        self.fading_bgm = core::mem::replace(&mut self.current_bgm, music);
+        self.crossfade_frames = CROSSFADE_FRAMES;
 
-    pub fn disrupt(&mut self) {
-        self.mixer.play_sound(SoundChannel::new(DISRUPT));
+        if state == BattleOrMenu::Battle {
+            self.intensity = 0;
+            self.intensity_beat = None;
+            self.in_danger = false;
+        }
+
+        self.update_music_volume();
     }
 
-    pub fn heal(&mut self) {
-        self.mixer.play_sound(SoundChannel::new(HEAL));
+    pub fn battle(&mut self) {
+        self.crossfade_to(BattleOrMenu::Battle);
     }
 
-    pub fn send_burst_shield(&mut self) {
-        self.mixer.play_sound(SoundChannel::new(SEND_BURST_SHIELD));
+    pub fn customise(&mut self) {
+        self.crossfade_to(BattleOrMenu::Menu);
     }
 
-    pub fn burst_shield_hit(&mut self) {
-        self.mixer.play_sound(SoundChannel::new(BURST_SHIELD_HIT));
+    pub fn title_screen(&mut self) {
+        self.crossfade_to(BattleOrMenu::Title);
     }
 }
@@ -0,0 +1,565 @@
+use agb::fixnum::Num;
+use agb::sound::mixer::{ChannelId, Mixer, SoundChannel};
+use core::slice::Iter;
+
+use super::voices::MAX_VOICES;
+
+/// Must match `BLOCK_SAMPLES` in `build.rs`
+const BLOCK_SAMPLES: usize = 1024;
+const BLOCK_HEADER_BYTES: usize = 4;
+const BLOCK_BYTES: usize = BLOCK_HEADER_BYTES + BLOCK_SAMPLES / 2;
+const TRACK_HEADER_BYTES: usize = 4;
+
+/// About 7 frames of audio. The mixer plays straight out of this while we decode into the part
+/// it has already played.
+const STREAM_BUFFER_SAMPLES: usize = 4096;
+/// The current and fading music, the battle intensity layer, the jukebox and every sound effect
+const MAX_STREAMS: usize = 4 + MAX_VOICES;
+/// Decoding a whole buffer at once would take most of a frame, so a stream which has just started
+/// fills up over a few frames instead. This is about two frames of audio, which is enough to stay
+/// ahead of the mixer even when a sound plays faster than normal.
+const MAX_DECODE_PER_UPDATE: usize = 1024;
+/// How many samples all the streams together get to decode each frame. Decoding a sample takes
+/// about 55 cycles with the default ROM wait states, so this is at most about 40% of a frame.
+/// That's enough for both pieces of music during a crossfade, the intensity layer and a sound
+/// effect or two. Anything past that plays on from its buffer and catches up in a later frame.
+const MAX_DECODE_PER_FRAME: usize = 2048;
+
+const INDEX_TABLE: [i32; 16] = [-1, -1, -1, -1, 2, 4, 6, 8, -1, -1, -1, -1, 2, 4, 6, 8];
+
+const STEP_TABLE: [i32; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66,
+    73, 80, 88, 97, 107, 118, 130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408, 449,
+    494, 544, 598, 658, 724, 796, 876, 963, 1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272,
+    2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132, 7845, 8630, 9493,
+    10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794, 32767,
+];
+
+/// What every nibble does at every step size, worked out ahead of time so that decoding a sample
+/// is a single lookup. Each step size has a row of 16 entries, one per nibble. An entry holds the
+/// change to the predictor shifted up by 12, and in the bottom 12 bits the offset of the row to
+/// use for the next sample.
+static DECODE_TABLE: [i32; STEP_TABLE.len() * 16] = decode_table();
+
+const fn decode_table() -> [i32; STEP_TABLE.len() * 16] {
+    let mut table = [0; STEP_TABLE.len() * 16];
+
+    let mut step_index = 0;
+    while step_index < STEP_TABLE.len() {
+        let step = STEP_TABLE[step_index];
+
+        let mut nibble = 0;
+        while nibble < 16 {
+            let mut delta = step >> 3;
+            if nibble & 4 != 0 {
+                delta += step;
+            }
+            if nibble & 2 != 0 {
+                delta += step >> 1;
+            }
+            if nibble & 1 != 0 {
+                delta += step >> 2;
+            }
+            if nibble & 8 != 0 {
+                delta = -delta;
+            }
+
+            let mut next_index = step_index as i32 + INDEX_TABLE[nibble];
+            if next_index < 0 {
+                next_index = 0;
+            } else if next_index > STEP_TABLE.len() as i32 - 1 {
+                next_index = STEP_TABLE.len() as i32 - 1;
+            }
+
+            table[step_index * 16 + nibble] = delta << 12 | next_index * 16;
+            nibble += 1;
+        }
+
+        step_index += 1;
+    }
+
+    table
+}
+
+/// Samples are written in pairs, see `Decoder::decode_into`
+#[link_section = ".ewram"]
+static mut STREAM_BUFFERS: [[u16; STREAM_BUFFER_SAMPLES / 2]; MAX_STREAMS] =
+    [[0; STREAM_BUFFER_SAMPLES / 2]; MAX_STREAMS];
+
+/// Audio encoded as IMA-ADPCM by `build.rs`
+pub struct AdpcmTrack {
+    data: &'static [u8],
+}
+
+impl AdpcmTrack {
+    pub const fn new(data: &'static [u8]) -> Self {
+        Self { data }
+    }
+
+    /// How many samples long the track is once decoded
+    pub fn samples(&self) -> usize {
+        u32::from_le_bytes([self.data[0], self.data[1], self.data[2], self.data[3]]) as usize
+    }
+}
+
+struct Decoder {
+    data: &'static [u8],
+    /// How many samples long the track is, kept here so the header isn't read for every byte
+    samples: usize,
+    /// The next sample which will be decoded, always even
+    sample: usize,
+    /// What is left of the block being decoded
+    bytes: Iter<'static, u8>,
+    predictor: i32,
+    /// Where the current step size's row starts in `DECODE_TABLE`
+    row: usize,
+}
+
+impl Decoder {
+    /// Decoding can only start at the beginning of a block, so this decodes forward from there
+    /// to `sample`, which must be even.
+    fn new(track: &'static AdpcmTrack, sample: usize) -> Self {
+        let samples = track.samples();
+        // an empty track has nothing to decode, which `decode_into` copes with
+        let sample = sample.checked_rem(samples).unwrap_or(0);
+
+        let mut decoder = Self {
+            data: track.data,
+            samples,
+            sample: sample / BLOCK_SAMPLES * BLOCK_SAMPLES,
+            bytes: [].iter(),
+            predictor: 0,
+            row: 0,
+        };
+
+        let mut skipped = [0; 64];
+        while decoder.sample < sample {
+            let pairs = ((sample - decoder.sample) / 2).min(skipped.len());
+            if decoder.decode_into(skipped.as_mut_ptr(), pairs, false) == 0 {
+                break;
+            }
+        }
+
+        decoder
+    }
+
+    fn start_block(&mut self, block: usize) {
+        let start = TRACK_HEADER_BYTES + block * BLOCK_BYTES;
+        let end = (start + BLOCK_BYTES).min(self.data.len());
+        let (header, bytes) = self.data[start..end].split_at(BLOCK_HEADER_BYTES);
+
+        self.predictor = i16::from_le_bytes([header[0], header[1]]) as i32;
+        self.row = (header[2] as usize).min(STEP_TABLE.len() - 1) * 16;
+        self.bytes = bytes.iter();
+        self.sample = block * BLOCK_SAMPLES;
+    }
+
+    /// Decodes the next `pairs` bytes into `output`, with the two samples from each byte packed
+    /// into a halfword so they can be written at once. `build.rs` pads tracks to an even length so
+    /// a byte never holds only one. Stops early at the end of a track which doesn't loop, and
+    /// returns how many pairs were decoded.
+    ///
+    /// This is most of the time spent on sound, so it runs from IWRAM where the code doesn't have
+    /// to be fetched 16 bits at a time through the ROM's wait states.
+    #[link_section = ".iwram"]
+    #[inline(never)]
+    fn decode_into(&mut self, output: *mut u16, pairs: usize, should_loop: bool) -> usize {
+        let mut decoded = 0;
+
+        while decoded < pairs {
+            if self.bytes.len() == 0 {
+                if self.sample >= self.samples {
+                    if !should_loop || self.samples == 0 {
+                        break;
+                    }
+
+                    self.start_block(0);
+                } else {
+                    self.start_block(self.sample / BLOCK_SAMPLES);
+                }
+
+                if self.bytes.len() == 0 {
+                    // the track is shorter than its header says
+                    break;
+                }
+            }
+
+            // work on a copy of the state so that it stays in registers
+            let bytes = self.bytes.as_slice();
+            let count = bytes.len().min(pairs - decoded);
+            let mut predictor = self.predictor;
+            let mut row = self.row;
+
+            for (i, &byte) in bytes[..count].iter().enumerate() {
+                let first = decode(byte & 0xf, &mut predictor, &mut row);
+                let second = decode(byte >> 4, &mut predictor, &mut row);
+
+                // Safety: the caller makes sure there's space for `pairs` in `output`
+                unsafe {
+                    output
+                        .add(decoded + i)
+                        .write_volatile(first as u8 as u16 | (second as u8 as u16) << 8)
+                };
+            }
+
+            self.bytes = bytes[count..].iter();
+            self.predictor = predictor;
+            self.row = row;
+            self.sample += count * 2;
+            decoded += count;
+        }
+
+        decoded
+    }
+}
+
+#[inline(always)]
+fn decode(nibble: u8, predictor: &mut i32, row: &mut usize) -> i8 {
+    // Safety: the row is always one of the table's and a nibble is less than 16
+    let entry = unsafe { *DECODE_TABLE.get_unchecked(*row + nibble as usize) };
+
+    *predictor = (*predictor + (entry >> 12)).clamp(i16::MIN as i32, i16::MAX as i32);
+    *row = (entry & 0xfff) as usize;
+
+    (*predictor >> 8) as i8
+}
+
+/// A track being decoded a few frames ahead into a buffer which the mixer plays on loop
+pub struct Stream {
+    decoder: Decoder,
+    buffer: usize,
+    channel: Option<ChannelId>,
+    should_loop: bool,
+
+    /// Always even, since samples are written in pairs
+    write_index: usize,
+    /// How many decoded samples are in the buffer which the mixer hasn't played yet
+    samples_ahead: usize,
+    last_read_index: usize,
+    /// Where the mixer has got to in the track
+    position: usize,
+    /// For tracks which don't loop, how many samples are left to play
+    remaining: Option<usize>,
+}
+
+impl Stream {
+    /// The position in the track which is currently being played, in samples
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn channel<'m>(&self, mixer: &'m mut Mixer) -> Option<&'m mut SoundChannel> {
+        match &self.channel {
+            Some(channel) => mixer.channel(channel),
+            None => None,
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.channel.is_none()
+    }
+
+    fn buffer_ptr(&self) -> *mut u16 {
+        // Safety: each buffer belongs to at most one stream at a time, see `StreamPool`
+        unsafe { STREAM_BUFFERS[self.buffer].as_mut_ptr() }
+    }
+
+    /// Decodes into the part of the buffer which the mixer has already played, but no more than
+    /// `limit` samples. Returns how many samples it wrote, including any silence.
+    fn fill(&mut self, limit: usize) -> usize {
+        let pairs = (STREAM_BUFFER_SAMPLES - self.samples_ahead).min(limit) / 2;
+        let mut written = 0;
+
+        // this takes two goes when the buffer wraps round
+        while written < pairs {
+            let start = self.write_index / 2;
+            let count = (pairs - written).min(STREAM_BUFFER_SAMPLES / 2 - start);
+
+            // Safety: `count` pairs from `start` fit before the end of the buffer, and the mixer
+            // has already played that part of it
+            let decoded = self.decoder.decode_into(
+                unsafe { self.buffer_ptr().add(start) },
+                count,
+                self.should_loop,
+            );
+            self.advance(decoded);
+
+            // a track which doesn't loop is followed by silence until it gets stopped
+            self.write_silence(count - decoded);
+            written += count;
+        }
+
+        pairs * 2
+    }
+
+    fn write_silence(&mut self, pairs: usize) {
+        for _ in 0..pairs {
+            // Safety: write_index is always inside the buffer
+            unsafe {
+                self.buffer_ptr()
+                    .add(self.write_index / 2)
+                    .write_volatile(0)
+            };
+            self.advance(1);
+        }
+    }
+
+    fn advance(&mut self, pairs: usize) {
+        self.write_index = (self.write_index + pairs * 2) % STREAM_BUFFER_SAMPLES;
+        self.samples_ahead += pairs * 2;
+    }
+
+    /// Decodes whatever the mixer played last frame, which is about 550 samples, plus some more
+    /// if the stream hasn't filled its buffer yet. What gets decoded comes out of `budget`.
+    fn update(&mut self, mixer: &mut Mixer, budget: &mut usize) {
+        let read_index = match self.channel(mixer) {
+            Some(channel) => channel.pos().floor() % STREAM_BUFFER_SAMPLES,
+            None => {
+                self.channel = None;
+                return;
+            }
+        };
+
+        let played =
+            (read_index + STREAM_BUFFER_SAMPLES - self.last_read_index) % STREAM_BUFFER_SAMPLES;
+        self.last_read_index = read_index;
+
+        if played > self.samples_ahead {
+            // Decoding has been held back for so long that the mixer has got past what was
+            // decoded and is playing old samples. Cutting the sound off is better than letting it
+            // stutter.
+            self.stop(mixer);
+            return;
+        }
+
+        self.samples_ahead -= played;
+        self.position = (self.position + played) % self.decoder.samples;
+
+        if let Some(remaining) = &mut self.remaining {
+            *remaining = remaining.saturating_sub(played);
+
+            if *remaining == 0 {
+                self.stop(mixer);
+                return;
+            }
+        }
+
+        *budget -= self.fill(MAX_DECODE_PER_UPDATE.min(*budget));
+    }
+
+    fn stop(&mut self, mixer: &mut Mixer) {
+        if let Some(channel) = self.channel(mixer) {
+            channel.stop();
+        }
+
+        self.channel = None;
+    }
+}
+
+/// Hands out the buffers the streams decode into
+#[derive(Default)]
+pub struct StreamPool {
+    in_use: [bool; MAX_STREAMS],
+    /// How many more samples the streams can decode this frame, see `MAX_DECODE_PER_FRAME`
+    budget: usize,
+}
+
+impl StreamPool {
+    /// Starts playing a track from `position`, in samples. Streams are decoded a byte at a time
+    /// so this can start a sample early.
+    pub fn play(
+        &mut self,
+        mixer: &mut Mixer,
+        track: &'static AdpcmTrack,
+        position: usize,
+        should_loop: bool,
+    ) -> Option<Stream> {
+        self.play_with(
+            mixer,
+            track,
+            position,
+            should_loop,
+            SoundChannel::new_high_priority,
+        )
+    }
+
+    /// Like `play`, but `make_channel` sets up the channel, for sounds which need their own
+    /// priority, volume, panning or speed
+    pub fn play_with(
+        &mut self,
+        mixer: &mut Mixer,
+        track: &'static AdpcmTrack,
+        position: usize,
+        should_loop: bool,
+        make_channel: impl FnOnce(&'static [u8]) -> SoundChannel,
+    ) -> Option<Stream> {
+        self.start(mixer, track, position, 0, should_loop, make_channel)
+    }
+
+    /// Plays a track once from the beginning after `delay` samples of silence, so that it can
+    /// start part way through one of the mixer's frames in time with something else. The delay
+    /// is at most half the stream's buffer, and like the position in `play` can be a sample short.
+    pub fn play_after(
+        &mut self,
+        mixer: &mut Mixer,
+        track: &'static AdpcmTrack,
+        delay: usize,
+        make_channel: impl FnOnce(&'static [u8]) -> SoundChannel,
+    ) -> Option<Stream> {
+        self.start(mixer, track, 0, delay, false, make_channel)
+    }
+
+    fn start(
+        &mut self,
+        mixer: &mut Mixer,
+        track: &'static AdpcmTrack,
+        position: usize,
+        delay: usize,
+        should_loop: bool,
+        make_channel: impl FnOnce(&'static [u8]) -> SoundChannel,
+    ) -> Option<Stream> {
+        let buffer = self.in_use.iter().position(|in_use| !in_use)?;
+
+        let samples = track.samples();
+        if samples == 0 {
+            return None;
+        }
+
+        // samples are decoded and written in pairs, so both of these need to be even
+        let position = position % samples / 2 * 2;
+        let delay = delay.min(STREAM_BUFFER_SAMPLES / 2) / 2 * 2;
+
+        let decoder = Decoder::new(track, position);
+        let mut stream = Stream {
+            // counts up through the silence to get to `position` as the track starts
+            position: (position + samples - delay % samples) % samples,
+            remaining: if should_loop {
+                None
+            } else {
+                Some(samples - position + delay)
+            },
+            decoder,
+            buffer,
+            channel: None,
+            should_loop,
+
+            write_index: 0,
+            samples_ahead: 0,
+            last_read_index: 0,
+        };
+
+        stream.write_silence(delay / 2);
+
+        // This doesn't come out of the budget. A sound should start straight away, and there's
+        // only ever a few started in a frame.
+        stream.fill(MAX_DECODE_PER_UPDATE);
+
+        // Safety: the mixer only reads from the buffer, and we only write to the part of it which
+        // has already been played. The buffer isn't reused until the channel has been stopped.
+        let buffer_data: &'static [u8] = unsafe {
+            core::slice::from_raw_parts(stream.buffer_ptr() as *const u8, STREAM_BUFFER_SAMPLES)
+        };
+
+        let mut channel = make_channel(buffer_data);
+        channel.should_loop().set_pos(Num::new(0));
+
+        stream.channel = Some(mixer.play_sound(channel)?);
+        self.in_use[buffer] = true;
+
+        Some(stream)
+    }
+
+    /// Should be called at the start of every frame, before any of the streams are updated
+    pub fn frame(&mut self) {
+        self.budget = MAX_DECODE_PER_FRAME;
+    }
+
+    /// Should be called every frame for every stream which is playing, before `Mixer::frame`.
+    /// Streams which are updated first get to decode first, so the most important ones should go
+    /// first. Returns false once a stream which doesn't loop has finished, or a stream has been
+    /// cut off for falling too far behind, after which it should be given back with `stop`.
+    pub fn update(&mut self, mixer: &mut Mixer, stream: &mut Stream) -> bool {
+        stream.update(mixer, &mut self.budget);
+        !stream.is_finished()
+    }
+
+    pub fn stop(&mut self, mixer: &mut Mixer, mut stream: Stream) {
+        stream.stop(mixer);
+        self.in_use[stream.buffer] = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    /// Encoded by `build.rs`, padded to an even length
+    static TEST_WAVE: AdpcmTrack =
+        AdpcmTrack::new(include_bytes!(concat!(env!("OUT_DIR"), "/test_wave.adpcm")));
+    const TEST_WAVE_SAMPLES: usize = 2502;
+
+    /// Must match `test_wave` in `build.rs`, but at the 8 bits the decoder puts out
+    fn test_wave(i: usize) -> i8 {
+        let phase = (i % 256) as i16;
+        let rising = if phase < 128 { phase } else { 256 - phase };
+
+        ((rising * 128 - 8192) >> 8) as i8
+    }
+
+    fn decode(from: usize, samples: usize, should_loop: bool) -> Vec<i8> {
+        let mut decoder = Decoder::new(&TEST_WAVE, from);
+        let mut output = vec![0u16; samples / 2];
+
+        let decoded = decoder.decode_into(output.as_mut_ptr(), output.len(), should_loop);
+
+        output[..decoded]
+            .iter()
+            .flat_map(|&pair| [pair as u8 as i8, (pair >> 8) as u8 as i8])
+            .collect()
+    }
+
+    #[test_case]
+    fn decodes_close_to_what_was_encoded(_gba: &mut agb::Gba) {
+        assert_eq!(TEST_WAVE.samples(), TEST_WAVE_SAMPLES);
+
+        let decoded = decode(0, TEST_WAVE_SAMPLES, false);
+        assert_eq!(decoded.len(), TEST_WAVE_SAMPLES);
+
+        // the step size starts off small and takes a few samples to catch up with the wave
+        for (i, &sample) in decoded.iter().enumerate().skip(64) {
+            let wanted = test_wave(i.min(TEST_WAVE_SAMPLES - 2));
+            assert!(
+                (sample as i32 - wanted as i32).abs() <= 2,
+                "sample {} decoded to {} instead of {}",
+                i,
+                sample,
+                wanted
+            );
+        }
+    }
+
+    #[test_case]
+    fn starting_part_way_through_decodes_the_same_samples(_gba: &mut agb::Gba) {
+        let whole = decode(0, TEST_WAVE_SAMPLES, false);
+
+        // in the first block, at the start of a block, and part way through a later block
+        for from in [2, BLOCK_SAMPLES, 1500] {
+            assert_eq!(decode(from, 256, false), whole[from..from + 256]);
+        }
+    }
+
+    #[test_case]
+    fn stops_at_the_end_unless_looping(_gba: &mut agb::Gba) {
+        let whole = decode(0, TEST_WAVE_SAMPLES, false);
+
+        assert_eq!(
+            decode(0, TEST_WAVE_SAMPLES + 100, false).len(),
+            TEST_WAVE_SAMPLES
+        );
+
+        let looped = decode(0, TEST_WAVE_SAMPLES + 100, true);
+        assert_eq!(looped[TEST_WAVE_SAMPLES..], whole[..100]);
+    }
+}
@@ -0,0 +1,192 @@
+use agb::fixnum::{num, Num};
+
+use super::adpcm::AdpcmTrack;
+use super::voices::Category;
+use super::Side;
+use crate::EnemyAttackType;
+
+/// A sound effect encoded by `build.rs`
+macro_rules! effect {
+    ($name:literal) => {
+        &AdpcmTrack::new(include_bytes!(concat!(
+            env!("OUT_DIR"),
+            "/",
+            $name,
+            ".adpcm"
+        )))
+    };
+}
+
+/// A variation of a sound effect made by `build.rs`
+macro_rules! variation {
+    ($name:literal, $index:literal) => {
+        &AdpcmTrack::new(include_bytes!(concat!(
+            env!("OUT_DIR"),
+            "/",
+            $name,
+            "_",
+            $index,
+            ".adpcm"
+        )))
+    };
+}
+
+pub(super) const DICE_ROLLS: &[&AdpcmTrack] = &[
+    effect!("SingleRoll_1"),
+    effect!("SingleRoll_2"),
+    effect!("SingleRoll_3"),
+    effect!("SingleRoll_4"),
+    effect!("SingleRoll_5"),
+];
+
+pub(super) const MULTI_ROLLS: &[&AdpcmTrack] = &[
+    effect!("MultiRoll_1"),
+    effect!("MultiRoll_2"),
+    effect!("MultiRoll_3"),
+    effect!("MultiRoll_4"),
+    effect!("MultiRoll_5"),
+];
+
+pub(super) const SHOOT: &[&AdpcmTrack] = &[
+    effect!("shoot"),
+    variation!("shoot", 1),
+    variation!("shoot", 2),
+    variation!("shoot", 3),
+];
+
+pub(super) const SHOT_HIT: &[&AdpcmTrack] = &[
+    effect!("shot_hit"),
+    variation!("shot_hit", 1),
+    variation!("shot_hit", 2),
+    variation!("shot_hit", 3),
+];
+
+pub(super) const SHIP_EXPLODE: &[&AdpcmTrack] = &[effect!("ship_explode")];
+pub(super) const MOVE_CURSOR: &[&AdpcmTrack] = &[effect!("move_cursor")];
+pub(super) const SELECT: &[&AdpcmTrack] = &[effect!("select")];
+pub(super) const BACK: &[&AdpcmTrack] = &[effect!("back")];
+pub(super) const ACCEPT: &[&AdpcmTrack] = &[effect!("accept")];
+pub(super) const SHIELD_DOWN: &[&AdpcmTrack] = &[effect!("shield_down")];
+pub(super) const SHIELD_UP: &[&AdpcmTrack] = &[effect!("shield_up")];
+pub(super) const SHIELD_DEFEND: &[&AdpcmTrack] = &[
+    effect!("shield_defend"),
+    variation!("shield_defend", 1),
+    variation!("shield_defend", 2),
+    variation!("shield_defend", 3),
+];
+pub(super) const DISRUPT: &[&AdpcmTrack] = &[effect!("disrupt")];
+pub(super) const HEAL: &[&AdpcmTrack] = &[effect!("heal")];
+pub(super) const SEND_BURST_SHIELD: &[&AdpcmTrack] = &[effect!("send_burst_shield")];
+pub(super) const BURST_SHIELD_HIT: &[&AdpcmTrack] = &[effect!("burst_shield_hit")];
+pub(super) const WARNING_TICK: &[&AdpcmTrack] = &[effect!("warning_tick")];
+
+/// Something happened which should make a noise
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SoundEvent {
+    Roll,
+    RollMulti,
+    Shoot(Side),
+    ShotHit(Side),
+    ShipExplode(Side),
+    ShieldUp(Side),
+    ShieldDown(Side),
+    ShieldDefend(Side),
+    Heal(Side),
+    Disrupt,
+    SendBurstShield,
+    BurstShieldHit,
+    /// An enemy attack is about to happen
+    AttackWarning(EnemyAttackType),
+    MoveCursor,
+    Select,
+    Back,
+    Accept,
+}
+
+impl SoundEvent {
+    /// Which ship the sound comes from, or `None` if it should play in the centre
+    pub(super) fn side(self) -> Option<Side> {
+        match self {
+            SoundEvent::Shoot(side)
+            | SoundEvent::ShotHit(side)
+            | SoundEvent::ShipExplode(side)
+            | SoundEvent::ShieldUp(side)
+            | SoundEvent::ShieldDown(side)
+            | SoundEvent::ShieldDefend(side)
+            | SoundEvent::Heal(side) => Some(side),
+            SoundEvent::Disrupt | SoundEvent::BurstShieldHit | SoundEvent::AttackWarning(_) => {
+                Some(Side::Enemy)
+            }
+            SoundEvent::SendBurstShield => Some(Side::Player),
+            SoundEvent::Roll
+            | SoundEvent::RollMulti
+            | SoundEvent::MoveCursor
+            | SoundEvent::Select
+            | SoundEvent::Back
+            | SoundEvent::Accept => None,
+        }
+    }
+}
+
+pub(super) struct SoundBankEntry {
+    /// One of these gets picked at random each time the sound plays
+    pub clips: &'static [&'static AdpcmTrack],
+    pub volume: Num<i16, 4>,
+    pub pitch: Num<usize, 8>,
+    /// How far the playback speed can randomly move away from normal in either direction
+    pub pitch_variation: Num<usize, 8>,
+    /// How far the volume can randomly move away from `volume`, as a fraction of it
+    pub volume_variation: Num<i16, 4>,
+    pub category: Category,
+}
+
+pub(super) fn entry_for(event: SoundEvent) -> SoundBankEntry {
+    let (clips, volume, pitch_variation, category) = match event {
+        SoundEvent::Roll => (DICE_ROLLS, num!(1.), num!(0.05), Category::Dice),
+        SoundEvent::RollMulti => (MULTI_ROLLS, num!(1.), num!(0.05), Category::Dice),
+        SoundEvent::Shoot(_) => (SHOOT, num!(1.), num!(0.05), Category::Combat),
+        SoundEvent::ShotHit(_) => (SHOT_HIT, num!(1.), num!(0.05), Category::Combat),
+        SoundEvent::ShipExplode(_) => (SHIP_EXPLODE, num!(1.), num!(0.), Category::Critical),
+        SoundEvent::ShieldUp(_) => (SHIELD_UP, num!(1.), num!(0.), Category::Combat),
+        SoundEvent::ShieldDown(_) => (SHIELD_DOWN, num!(1.), num!(0.), Category::Critical),
+        SoundEvent::ShieldDefend(_) => (SHIELD_DEFEND, num!(0.5), num!(0.05), Category::Combat),
+        SoundEvent::Heal(_) => (HEAL, num!(1.), num!(0.), Category::Combat),
+        SoundEvent::Disrupt => (DISRUPT, num!(1.), num!(0.), Category::Combat),
+        SoundEvent::SendBurstShield => (SEND_BURST_SHIELD, num!(1.), num!(0.), Category::Combat),
+        SoundEvent::BurstShieldHit => (BURST_SHIELD_HIT, num!(1.), num!(0.), Category::Critical),
+        SoundEvent::AttackWarning(_) => (WARNING_TICK, num!(0.5), num!(0.), Category::Combat),
+        SoundEvent::MoveCursor => (MOVE_CURSOR, num!(0.5), num!(0.), Category::Ui),
+        SoundEvent::Select => (SELECT, num!(0.75), num!(0.), Category::Ui),
+        SoundEvent::Back => (BACK, num!(0.5), num!(0.), Category::Ui),
+        SoundEvent::Accept => (ACCEPT, num!(0.5), num!(0.), Category::Ui),
+    };
+
+    // sounds which play over and over during a battle get louder and quieter as well
+    let volume_variation = match event {
+        SoundEvent::Roll
+        | SoundEvent::RollMulti
+        | SoundEvent::Shoot(_)
+        | SoundEvent::ShotHit(_)
+        | SoundEvent::ShieldDefend(_) => num!(0.125),
+        _ => num!(0.),
+    };
+
+    // each type of attack gets its own tone so they can be told apart without looking
+    let pitch = match event {
+        SoundEvent::AttackWarning(attack) => match attack {
+            EnemyAttackType::Attack => num!(1.5),
+            EnemyAttackType::Shield => num!(1.),
+            EnemyAttackType::Heal => num!(0.75),
+        },
+        _ => num!(1.),
+    };
+
+    SoundBankEntry {
+        clips,
+        volume,
+        pitch,
+        pitch_variation,
+        volume_variation,
+        category,
+    }
+}
@@ -0,0 +1,276 @@
+use agb::sound::mixer::{Mixer, SoundChannel};
+use alloc::vec::Vec;
+
+use super::adpcm::{AdpcmTrack, Stream, StreamPool};
+
+/// The mixer has 8 channels and the music can take up to 3 of them during a crossfade into
+/// battle, so sound effects get to use the rest.
+pub(super) const MAX_VOICES: usize = 5;
+/// Playing the same sound lots of times at once just makes it louder, so cap how many can overlap
+const MAX_COPIES_OF_ONE_SOUND: usize = 2;
+
+/// How important a sound is. Sounds in a higher category can steal a voice from a lower one.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Category {
+    Dice,
+    Ui,
+    Combat,
+    Critical,
+}
+
+/// A sound effect which was played, for changing it while it's still playing
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct VoiceId(u32);
+
+struct Voice {
+    id: VoiceId,
+    stream: Stream,
+    /// All the recordings of the sound, so that variations of it count as the same sound
+    sound: &'static [&'static AdpcmTrack],
+    category: Category,
+    started: u32,
+}
+
+/// Keeps track of which sound effects are playing so that when the mixer runs out of
+/// channels, the least important sound gets cut off rather than whatever happens to be newest.
+#[derive(Default)]
+pub struct VoiceManager {
+    voices: Vec<Voice>,
+    frame: u32,
+    next_id: u32,
+}
+
+impl VoiceManager {
+    /// Decodes the next part of every sound which is playing
+    pub fn frame(&mut self, mixer: &mut Mixer, streams: &mut StreamPool) {
+        self.frame = self.frame.wrapping_add(1);
+
+        for voice in &mut self.voices {
+            streams.update(mixer, &mut voice.stream);
+        }
+
+        self.forget_finished(mixer, streams);
+    }
+
+    /// Plays `clip`, which is one of the recordings in `sound`. `make_channel` sets up its
+    /// volume, panning and speed.
+    pub fn play(
+        &mut self,
+        mixer: &mut Mixer,
+        streams: &mut StreamPool,
+        sound: &'static [&'static AdpcmTrack],
+        clip: &'static AdpcmTrack,
+        category: Category,
+        make_channel: impl FnOnce(&'static [u8]) -> SoundChannel,
+    ) -> Option<VoiceId> {
+        self.forget_finished(mixer, streams);
+
+        let copies = self
+            .voices
+            .iter()
+            .filter(|voice| core::ptr::eq(voice.sound, sound))
+            .count();
+
+        let to_steal = if copies >= MAX_COPIES_OF_ONE_SOUND {
+            self.oldest(|voice| core::ptr::eq(voice.sound, sound))
+        } else if self.voices.len() >= MAX_VOICES {
+            self.least_important(category)
+        } else {
+            None
+        };
+
+        if let Some(index) = to_steal {
+            self.stop(mixer, streams, index);
+        } else if self.voices.len() >= MAX_VOICES {
+            // everything playing is more important than this
+            return None;
+        }
+
+        let stream = streams.play_with(mixer, clip, 0, false, make_channel)?;
+
+        let id = VoiceId(self.next_id);
+        self.next_id = self.next_id.wrapping_add(1);
+
+        self.voices.push(Voice {
+            id,
+            stream,
+            sound,
+            category,
+            started: self.frame,
+        });
+
+        Some(id)
+    }
+
+    /// The channel a sound is playing on, or `None` if it has finished or been cut off
+    pub fn channel<'m>(&self, mixer: &'m mut Mixer, id: VoiceId) -> Option<&'m mut SoundChannel> {
+        self.voices
+            .iter()
+            .find(|voice| voice.id == id)?
+            .stream
+            .channel(mixer)
+    }
+
+    fn forget_finished(&mut self, mixer: &mut Mixer, streams: &mut StreamPool) {
+        let mut index = 0;
+
+        while index < self.voices.len() {
+            if self.voices[index].stream.channel(mixer).is_some() {
+                index += 1;
+            } else {
+                self.stop(mixer, streams, index);
+            }
+        }
+    }
+
+    fn oldest(&self, predicate: impl Fn(&Voice) -> bool) -> Option<usize> {
+        self.voices
+            .iter()
+            .enumerate()
+            .filter(|(_, voice)| predicate(voice))
+            .max_by_key(|(_, voice)| self.frame.wrapping_sub(voice.started))
+            .map(|(index, _)| index)
+    }
+
+    /// The oldest voice in the lowest category, as long as that isn't above `category`
+    fn least_important(&self, category: Category) -> Option<usize> {
+        let lowest = self.voices.iter().map(|voice| voice.category).min()?;
+
+        if lowest > category {
+            return None;
+        }
+
+        self.oldest(|voice| voice.category == lowest)
+    }
+
+    fn stop(&mut self, mixer: &mut Mixer, streams: &mut StreamPool, index: usize) {
+        let voice = self.voices.swap_remove(index);
+        streams.stop(mixer, voice.stream);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The track header and two blocks, each with a header and 1024 samples
+    const SILENCE_BYTES: usize = 4 + 2 * (4 + 1024 / 2);
+
+    /// A track 2048 samples long which is all zeros, which decodes to silence
+    const fn silence() -> [u8; SILENCE_BYTES] {
+        let mut data = [0; SILENCE_BYTES];
+        let samples = 2048u32.to_le_bytes();
+
+        let mut i = 0;
+        while i < samples.len() {
+            data[i] = samples[i];
+            i += 1;
+        }
+
+        data
+    }
+
+    static SILENCE_DATA: [u8; SILENCE_BYTES] = silence();
+    static SILENCE: AdpcmTrack = AdpcmTrack::new(&SILENCE_DATA);
+    /// Different sounds are told apart by where their list of recordings is, so these are all
+    /// different sounds even though they're the same recording
+    static SOUNDS: [[&AdpcmTrack; 1]; MAX_VOICES + 1] = [[&SILENCE]; MAX_VOICES + 1];
+
+    struct Voices {
+        mixer: Mixer,
+        streams: StreamPool,
+        voices: VoiceManager,
+    }
+
+    impl Voices {
+        fn new(gba: &mut agb::Gba) -> Self {
+            Self {
+                mixer: gba.mixer.mixer(),
+                streams: StreamPool::default(),
+                voices: VoiceManager::default(),
+            }
+        }
+
+        /// Plays the sound a frame after the last one, so that they all have different ages
+        fn play(&mut self, sound: usize, category: Category) -> Option<VoiceId> {
+            self.streams.frame();
+            self.voices.frame(&mut self.mixer, &mut self.streams);
+
+            self.voices.play(
+                &mut self.mixer,
+                &mut self.streams,
+                &SOUNDS[sound],
+                &SILENCE,
+                category,
+                SoundChannel::new,
+            )
+        }
+
+        fn is_playing(&mut self, id: VoiceId) -> bool {
+            self.voices.channel(&mut self.mixer, id).is_some()
+        }
+    }
+
+    #[test_case]
+    fn steals_the_oldest_of_the_least_important_voices(gba: &mut agb::Gba) {
+        let mut voices = Voices::new(gba);
+
+        let ui = voices.play(0, Category::Ui).unwrap();
+        let oldest_dice = voices.play(1, Category::Dice).unwrap();
+        let newer_dice = voices.play(2, Category::Dice).unwrap();
+        let combat = voices.play(3, Category::Combat).unwrap();
+        let critical = voices.play(4, Category::Critical).unwrap();
+
+        let stealer = voices.play(5, Category::Combat).unwrap();
+
+        assert!(!voices.is_playing(oldest_dice));
+        for id in [ui, newer_dice, combat, critical, stealer] {
+            assert!(voices.is_playing(id));
+        }
+    }
+
+    #[test_case]
+    fn sounds_can_steal_from_their_own_category(gba: &mut agb::Gba) {
+        let mut voices = Voices::new(gba);
+
+        let playing: Vec<_> = (0..MAX_VOICES)
+            .map(|sound| voices.play(sound, Category::Ui).unwrap())
+            .collect();
+
+        let stealer = voices.play(MAX_VOICES, Category::Ui).unwrap();
+
+        assert!(!voices.is_playing(playing[0]));
+        assert!(playing[1..].iter().all(|&id| voices.is_playing(id)));
+        assert!(voices.is_playing(stealer));
+    }
+
+    #[test_case]
+    fn less_important_sounds_are_dropped_when_the_voices_are_full(gba: &mut agb::Gba) {
+        let mut voices = Voices::new(gba);
+
+        let playing: Vec<_> = (0..MAX_VOICES)
+            .map(|sound| voices.play(sound, Category::Combat).unwrap())
+            .collect();
+
+        assert_eq!(voices.play(MAX_VOICES, Category::Dice), None);
+        assert!(playing.iter().all(|&id| voices.is_playing(id)));
+    }
+
+    #[test_case]
+    fn too_many_copies_of_a_sound_cut_off_the_oldest_copy(gba: &mut agb::Gba) {
+        let mut voices = Voices::new(gba);
+
+        let copies: Vec<_> = (0..MAX_COPIES_OF_ONE_SOUND)
+            .map(|_| voices.play(0, Category::Critical).unwrap())
+            .collect();
+        let other = voices.play(1, Category::Dice).unwrap();
+
+        // even though there are voices free, and the other sound is less important
+        let newest = voices.play(0, Category::Critical).unwrap();
+
+        assert!(!voices.is_playing(copies[0]));
+        assert!(copies[1..].iter().all(|&id| voices.is_playing(id)));
+        assert!(voices.is_playing(other));
+        assert!(voices.is_playing(newest));
+    }
+}
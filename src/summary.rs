@@ -0,0 +1,109 @@
+use agb::display::object::Object;
+use agb::input::Button;
+use alloc::vec::Vec;
+
+use crate::graphics::{NumberDisplay, TextDisplay, FACE_SPRITES};
+use crate::sfx::SoundEvent;
+use crate::ui::Icons;
+use crate::{Agb, PlayerDice};
+
+/// Five nets side by side or stacked won't fit next to the stats, so each die gets a row with its
+/// faces in order instead
+const DICE_X: i32 = 4;
+const DICE_Y: i32 = 38;
+const FACE_SIZE: i32 = 24;
+const STATS_X: i32 = 156;
+/// Numbers grow to the left from here, so they line up on the right
+const VALUES_X: i32 = 228;
+
+/// Shows how the run went once the player's ship has been destroyed, and waits for them to be
+/// done looking at it
+pub(crate) fn run_summary(agb: &mut Agb, dice: &PlayerDice, level: u32, new_best: bool) {
+    let obj = &agb.obj;
+    let run = agb.progress.run.clone();
+
+    let mut title = TextDisplay::new((120 - 9 * 2, 12).into());
+    title.set_text("game over", obj);
+
+    let mut labels = Vec::new();
+    let mut values = Vec::new();
+
+    let rows = [
+        ("level", level),
+        ("battles won", run.battles_won),
+        ("damage dealt", run.damage_dealt),
+    ];
+
+    for (i, (label, value)) in rows.iter().enumerate() {
+        let y = 52 + i as i32 * 14;
+
+        let mut label_display = TextDisplay::new((STATS_X, y).into());
+        label_display.set_text(label, obj);
+        labels.push(label_display);
+
+        let mut value_display = NumberDisplay::new((VALUES_X, y).into());
+        value_display.set_value(Some(*value), obj);
+        values.push(value_display);
+    }
+
+    let mut new_best_text = TextDisplay::new((STATS_X + 8, 60).into());
+    new_best_text.set_text("new best!", obj);
+
+    let mut most_used_text = TextDisplay::new((STATS_X, 94).into());
+    let mut _most_used_icon: Option<Object> = None;
+    if let Some(face) = run.most_used_face() {
+        most_used_text.set_text("most used", obj);
+
+        let mut icon = obj.object(obj.sprite(FACE_SPRITES.sprite_for_face(face)));
+        icon.set_position((VALUES_X - 24, 104).into()).show();
+        _most_used_icon = Some(icon);
+    }
+
+    let mut dice_text = TextDisplay::new((DICE_X + 4, DICE_Y - 10).into());
+    dice_text.set_text("final dice", obj);
+
+    let mut faces = Icons::default();
+    faces.set(
+        dice.dice.iter().enumerate().flat_map(|(row, die)| {
+            die.faces.iter().enumerate().map(move |(column, &face)| {
+                (
+                    (
+                        DICE_X + column as i32 * FACE_SIZE,
+                        DICE_Y + row as i32 * FACE_SIZE,
+                    )
+                        .into(),
+                    FACE_SPRITES.sprite_for_face(face),
+                )
+            })
+        }),
+        obj,
+    );
+
+    let mut continue_text = TextDisplay::new((STATS_X + 8, 148).into());
+    continue_text.set_text("press a", obj);
+
+    let mut input = agb::input::ButtonController::new();
+    let mut counter = 0usize;
+
+    loop {
+        counter = counter.wrapping_add(1);
+        input.update();
+
+        if new_best && (counter / 16) % 2 == 0 {
+            new_best_text.show();
+        } else {
+            new_best_text.hide();
+        }
+
+        if input.is_just_pressed(Button::A) || input.is_just_pressed(Button::START) {
+            agb.sfx.play(SoundEvent::Accept);
+            break;
+        }
+
+        agb.star_background.update();
+        agb.sfx.frame();
+        agb.vblank.wait_for_vblank();
+        agb.obj.commit();
+        agb.star_background.commit(&mut agb.vram);
+    }
+}
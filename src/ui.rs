@@ -0,0 +1,261 @@
+//! Widgets for screens which are built out of objects.
+//!
+//! Widgets keep hold of their objects between frames and only touch the ones which change, and
+//! [`Focus`] gives every screen the same cursor movement and sounds.
+
+use agb::display::object::{Object, ObjectController, Sprite};
+use agb::fixnum::Vector2D;
+use agb::input::{Button, ButtonController, Tri};
+use alloc::vec::Vec;
+
+use crate::graphics::{SELECT_BOX, SMALL_SPRITES};
+use crate::sfx::{Sfx, SoundEvent};
+
+/// A group of sprites which stay on screen between frames
+#[derive(Default)]
+pub(crate) struct Icons<'a> {
+    icons: Vec<(Object<'a>, &'static Sprite)>,
+}
+
+impl<'a> Icons<'a> {
+    /// Shows exactly these icons, reusing the objects which are already there and only changing
+    /// the sprite of the ones which need a different one
+    pub fn set(
+        &mut self,
+        icons: impl IntoIterator<Item = (Vector2D<i32>, &'static Sprite)>,
+        obj: &'a ObjectController,
+    ) {
+        let mut count = 0;
+
+        for (position, sprite) in icons {
+            if let Some((object, shown)) = self.icons.get_mut(count) {
+                if !core::ptr::eq(*shown, sprite) {
+                    object.set_sprite(obj.sprite(sprite));
+                    *shown = sprite;
+                }
+
+                object.set_position(position);
+            } else {
+                let mut object = obj.object(obj.sprite(sprite));
+                object.set_position(position).show();
+                self.icons.push((object, sprite));
+            }
+
+            count += 1;
+        }
+
+        self.icons.truncate(count);
+    }
+
+    pub fn clear(&mut self) {
+        self.icons.clear();
+    }
+}
+
+/// How the cursor of a [`Focus`] moves between items
+#[derive(Clone, Copy)]
+pub(crate) enum Navigation {
+    /// Left and right move along a single row, wrapping around at the ends
+    Row,
+    /// Up and down move along a single column, wrapping around at the ends
+    Column,
+    /// Items go left to right and then top to bottom, `columns` to a row
+    Grid { columns: usize },
+    /// For layouts which aren't a grid, like the net of a die
+    Custom {
+        lr: fn(usize, Tri) -> usize,
+        ud: fn(usize, Tri) -> usize,
+    },
+}
+
+impl Navigation {
+    fn step(self, index: usize, len: usize, lr: Tri, ud: Tri) -> usize {
+        let wrap = |index: isize| index.rem_euclid(len as isize) as usize;
+
+        match self {
+            Navigation::Row => wrap(index as isize + lr as isize),
+            Navigation::Column => wrap(index as isize + ud as isize),
+            Navigation::Grid { columns } => {
+                wrap(index as isize + lr as isize + ud as isize * columns as isize)
+            }
+            Navigation::Custom {
+                lr: move_lr,
+                ud: move_ud,
+            } => move_ud(move_lr(index, lr), ud),
+        }
+    }
+}
+
+/// What the cursor of a [`Focus`] is drawn with
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum CursorStyle {
+    /// The animated box which goes around dice faces
+    SelectBox,
+    /// A `>` for lists of text
+    Pointer,
+}
+
+/// The sounds a [`Focus`] plays by itself. Leave one as `None` if the screen needs to decide
+/// what to play, for example when a selection isn't allowed.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) struct FocusSounds {
+    pub moved: Option<SoundEvent>,
+    pub selected: Option<SoundEvent>,
+    pub back: Option<SoundEvent>,
+}
+
+impl FocusSounds {
+    pub const MENU: Self = Self {
+        moved: Some(SoundEvent::MoveCursor),
+        selected: Some(SoundEvent::Select),
+        back: Some(SoundEvent::Back),
+    };
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum FocusEvent {
+    /// The cursor moved to a different item, and neither A nor B was pressed
+    Moved,
+    /// A was pressed on the item with this index
+    Selected(usize),
+    Back,
+}
+
+/// A cursor which moves between items on the screen
+pub(crate) struct Focus<'a> {
+    navigation: Navigation,
+    /// Where the cursor goes for each item
+    positions: Vec<Vector2D<i32>>,
+    index: usize,
+    style: CursorStyle,
+    sounds: FocusSounds,
+    cursor: Object<'a>,
+    frame: usize,
+    /// Added to every position, for screens which shake
+    offset: Vector2D<i32>,
+}
+
+impl<'a> Focus<'a> {
+    pub fn new(
+        navigation: Navigation,
+        positions: Vec<Vector2D<i32>>,
+        style: CursorStyle,
+        sounds: FocusSounds,
+        obj: &'a ObjectController,
+    ) -> Self {
+        let sprite = match style {
+            CursorStyle::SelectBox => SELECT_BOX.sprite(0),
+            CursorStyle::Pointer => SMALL_SPRITES.character('>').unwrap(),
+        };
+
+        let mut focus = Self {
+            navigation,
+            positions,
+            index: 0,
+            style,
+            sounds,
+            cursor: obj.object(obj.sprite(sprite)),
+            frame: 0,
+            offset: (0, 0).into(),
+        };
+
+        focus.place_cursor();
+        focus.cursor.show();
+        focus
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn set_index(&mut self, index: usize) {
+        self.index = index.min(self.positions.len().saturating_sub(1));
+        self.place_cursor();
+    }
+
+    /// For when items get added, removed or move around
+    pub fn set_positions(&mut self, positions: Vec<Vector2D<i32>>) {
+        self.positions = positions;
+        self.set_index(self.index);
+    }
+
+    pub fn set_offset(&mut self, offset: Vector2D<i32>) {
+        self.offset = offset;
+        self.place_cursor();
+    }
+
+    pub fn show(&mut self) {
+        self.cursor.show();
+    }
+
+    pub fn hide(&mut self) {
+        self.cursor.hide();
+    }
+
+    /// The cursor moves even on frames where A or B is pressed. It moves first, so A selects the
+    /// item it lands on, and the `Selected` or `Back` is returned instead of `Moved`.
+    pub fn update(
+        &mut self,
+        input: &ButtonController,
+        sfx: &mut Sfx,
+        obj: &'a ObjectController,
+    ) -> Option<FocusEvent> {
+        self.frame = self.frame.wrapping_add(1);
+        if self.style == CursorStyle::SelectBox {
+            self.cursor
+                .set_sprite(obj.sprite(SELECT_BOX.animation_sprite(self.frame / 10)));
+        }
+
+        if self.positions.is_empty() {
+            return None;
+        }
+
+        let lr: Tri = (
+            input.is_just_pressed(Button::LEFT),
+            input.is_just_pressed(Button::RIGHT),
+        )
+            .into();
+        let ud: Tri = (
+            input.is_just_pressed(Button::UP),
+            input.is_just_pressed(Button::DOWN),
+        )
+            .into();
+
+        let index = self
+            .navigation
+            .step(self.index, self.positions.len(), lr, ud);
+        let moved = index != self.index;
+
+        if moved {
+            self.index = index;
+            self.play(self.sounds.moved, sfx);
+        }
+
+        let event = if input.is_just_pressed(Button::A) {
+            self.play(self.sounds.selected, sfx);
+            Some(FocusEvent::Selected(self.index))
+        } else if input.is_just_pressed(Button::B) {
+            self.play(self.sounds.back, sfx);
+            Some(FocusEvent::Back)
+        } else if moved {
+            Some(FocusEvent::Moved)
+        } else {
+            None
+        };
+
+        self.place_cursor();
+        event
+    }
+
+    fn play(&self, sound: Option<SoundEvent>, sfx: &mut Sfx) {
+        if let Some(sound) = sound {
+            sfx.play(sound);
+        }
+    }
+
+    fn place_cursor(&mut self) {
+        if let Some(&position) = self.positions.get(self.index) {
+            self.cursor.set_position(position + self.offset);
+        }
+    }
+}